@@ -1,42 +1,725 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{DeriveInput, LitStr, parse_macro_input};
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprArray, Fields, Ident, Lit, LitInt, LitStr, UnOp,
+    parse_macro_input, parse_quote,
+};
+
+/// Maps a `#[config(format = "...")]` value to the matching `ConfigFormat` variant.
+fn format_variant(format: &str) -> Option<&'static str> {
+    match format {
+        "json" => Some("Json"),
+        "toml" => Some("Toml"),
+        "yaml" | "yml" => Some("Yaml"),
+        "ron" => Some("Ron"),
+        "json5" => Some("Json5"),
+        "ini" => Some("Ini"),
+        "dotenv" | "env" => Some("Dotenv"),
+        _ => None,
+    }
+}
+
+/// Gets the mtc-config cargo feature enabling a `ConfigFormat` variant.
+fn format_feature(variant: &str) -> &'static str {
+    match variant {
+        "Json" => "json",
+        "Toml" => "toml",
+        "Yaml" => "yaml",
+        "Ron" => "ron",
+        "Json5" => "json5",
+        "Ini" => "ini",
+        _ => "dotenv",
+    }
+}
+
+/// Keys accepted inside `#[config(...)]`.
+const KNOWN_KEYS: &[&str] = &[
+    "name",
+    "format",
+    "migrate",
+    "history",
+    "global",
+    "default_file",
+    "encrypted",
+    "integrity",
+    "env_prefix",
+    "multi",
+    "section",
+    "file",
+    "remote",
+    "codec",
+];
+
+/// Keys accepted inside `#[config(...)]` on a field.
+const KNOWN_FIELD_KEYS: &[&str] = &["secret", "nested", "min", "max", "non_empty", "one_of"];
+
+/// Formats accepted by `#[config(format = "...")]`, for error messages.
+const KNOWN_FORMATS: &str = "json, toml, yaml, ron, json5, ini, dotenv";
+
+/// Edit distance between two short identifiers, used for "did you mean" hints.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Suggests the closest candidate to a misspelled value, if any is close enough.
+fn suggestion<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(value, candidate), candidate))
+        .filter(|(distance, _)| *distance <= (value.len() / 3).max(1))
+        .min()
+        .map(|(_, candidate)| format!(", did you mean `{candidate}`?"))
+        .unwrap_or_default()
+}
+
+/// Converts a type name to snake_case, keeping acronyms together:
+/// `HttpServerConfig` and `HTTPServerConfig` both become `http_server_config`.
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev != '_'
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower))
+            {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Joins the `///` doc comment lines of an item, trimming the leading space.
+fn doc_comment(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(line) => Some(line.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Renders a type the way it is usually written, `Vec<String>` rather than
+/// the token spacing `Vec < String >`.
+fn type_name(ty: &syn::Type) -> String {
+    let mut out = quote!(#ty).to_string();
+    for (from, to) in [
+        (" < ", "<"),
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" ,", ","),
+        ("& ", "&"),
+        ("[ ", "["),
+        (" ]", "]"),
+        (" ;", ";"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        out = out.replace(from, to);
+    }
+    out
+}
+
+/// Consumes the value of a nested meta item that is not of interest.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        let _: Expr = meta.value()?.parse()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta(&nested))?;
+    }
+    Ok(())
+}
+
+/// The serde options that change how a field is serialized.
+#[derive(Default)]
+struct SerdeOptions {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+}
+
+/// Reads the `#[serde(...)]` options relevant to the schema; anything else,
+/// including malformed attributes, is left for serde to report.
+fn serde_options(attrs: &[Attribute]) -> SerdeOptions {
+    let mut options = SerdeOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("rename_all") && meta.input.peek(syn::Token![=]) {
+                options.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("skip") {
+                options.skip = true;
+            } else {
+                skip_meta(&meta)?;
+            }
+            Ok(())
+        });
+    }
+    options
+}
+
+/// Applies a serde `rename_all` rule to a snake_case field name.
+fn rename_field(name: &str, rule: &str) -> String {
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    };
+    match rule {
+        "lowercase" => name.replace('_', ""),
+        "UPPERCASE" => name.replace('_', "").to_uppercase(),
+        "PascalCase" => name.split('_').map(capitalize).collect(),
+        "camelCase" => {
+            let pascal: String = name
+                .split('_')
+                .map(capitalize)
+                .collect::<Vec<String>>()
+                .concat();
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "SCREAMING_SNAKE_CASE" => name.to_uppercase(),
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.replace('_', "-").to_uppercase(),
+        _ => name.to_string(),
+    }
+}
+
+/// Parses a possibly negative integer or float literal.
+fn parse_number(meta: &ParseNestedMeta) -> syn::Result<f64> {
+    let expr: Expr = meta.value()?.parse()?;
+    let (negative, literal) = match &expr {
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => (true, &*unary.expr),
+        other => (false, other),
+    };
+    let value = match literal {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(n), ..
+        }) => n.base10_parse::<f64>()?,
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Float(n), ..
+        }) => n.base10_parse::<f64>()?,
+        _ => return Err(syn::Error::new(expr.span(), "expected a number")),
+    };
+    Ok(if negative { -value } else { value })
+}
+
+/// Generates the `FieldSchema` expression of a named field, reading its
+/// default from the `__defaults` variable of the generated function.
+fn field_schema(
+    field: &syn::Field,
+    rename_all: Option<&str>,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let serde = serde_options(&field.attrs);
+    if serde.skip {
+        return Ok(None);
+    }
+    let ident = field
+        .ident
+        .as_ref()
+        .expect("named field")
+        .unraw()
+        .to_string();
+    let serialized = serde
+        .rename
+        .unwrap_or_else(|| rename_all.map_or(ident.clone(), |rule| rename_field(&ident, rule)));
+
+    let mut secret = false;
+    let mut nested = false;
+    let mut rules = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("config"))
+    {
+        let parsed = attr.parse_nested_meta(|meta| {
+            let key = meta
+                .path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .unwrap_or_default();
+            if KNOWN_FIELD_KEYS.contains(&key.as_str()) {
+                if seen.contains(&key) {
+                    return Err(meta.error(format!("duplicate config attribute `{key}`")));
+                }
+                seen.push(key.clone());
+            }
+            match key.as_str() {
+                "secret" => secret = true,
+                "nested" => nested = true,
+                "non_empty" => rules.push(quote!(mtc_config::ValidationRule::NonEmpty)),
+                "min" => {
+                    let min = parse_number(&meta)?;
+                    rules.push(quote!(mtc_config::ValidationRule::Min(#min)));
+                }
+                "max" => {
+                    let max = parse_number(&meta)?;
+                    rules.push(quote!(mtc_config::ValidationRule::Max(#max)));
+                }
+                "one_of" => {
+                    let array: ExprArray = meta.value()?.parse()?;
+                    let allowed = array
+                        .elems
+                        .iter()
+                        .map(|elem| match elem {
+                            Expr::Lit(syn::ExprLit {
+                                lit: Lit::Str(s), ..
+                            }) => Ok(s.clone()),
+                            other => {
+                                Err(syn::Error::new(other.span(), "expected a string literal"))
+                            }
+                        })
+                        .collect::<syn::Result<Vec<_>>>()?;
+                    rules.push(quote!(mtc_config::ValidationRule::OneOf(
+                        vec![#(#allowed),*]
+                    )));
+                }
+                _ => {
+                    let path = meta.path.clone();
+                    skip_meta(&meta)?;
+                    return Err(syn::Error::new(
+                        path.span(),
+                        format!(
+                            "unknown config field attribute `{key}`, expected one of: {}{}",
+                            KNOWN_FIELD_KEYS.join(", "),
+                            suggestion(&key, KNOWN_FIELD_KEYS.iter().copied())
+                        ),
+                    ));
+                }
+            }
+            Ok(())
+        });
+        if let Err(err) = parsed {
+            match errors.as_mut() {
+                Some(errors) => errors.combine(err),
+                None => errors = Some(err),
+            }
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let ty = &field.ty;
+    let type_str = type_name(ty);
+    let doc = doc_comment(&field.attrs);
+    let nested = if nested {
+        quote! {
+            Some(<#ty as mtc_config::ConfigSection>::section_schema(
+                mtc_config::ConfigSchema::default_field(__defaults, #serialized),
+            ))
+        }
+    } else {
+        quote!(None)
+    };
+    Ok(Some(quote! {
+        mtc_config::FieldSchema {
+            name: #serialized,
+            type_name: #type_str,
+            doc: #doc,
+            default: mtc_config::ConfigSchema::default_field(__defaults, #serialized)
+                .map(|value| value.to_string()),
+            rules: vec![#(#rules),*],
+            secret: #secret,
+            nested: #nested,
+            value_format: {
+                use mtc_config::{HasFormat as _, NoFormat as _};
+                (&mtc_config::FormatOf::<#ty>::new()).format()
+            },
+        }
+    }))
+}
+
+/// Generates the `ConfigSchema` expression of a type, reading defaults from
+/// a `__defaults: Option<&Value>` variable.
+fn schema_expr(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let type_str = input.ident.unraw().to_string();
+    let doc = doc_comment(&input.attrs);
+    let rename_all = serde_options(&input.attrs).rename_all;
+
+    let mut fields = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    if let Data::Struct(data) = &input.data
+        && let Fields::Named(named) = &data.fields
+    {
+        for field in &named.named {
+            match field_schema(field, rename_all.as_deref()) {
+                Ok(Some(field)) => fields.push(field),
+                Ok(None) => {}
+                Err(err) => match errors.as_mut() {
+                    Some(errors) => errors.combine(err),
+                    None => errors = Some(err),
+                },
+            }
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    Ok(quote! {
+        mtc_config::ConfigSchema {
+            type_name: #type_str,
+            doc: #doc,
+            fields: vec![#(#fields),*],
+        }
+    })
+}
+
+/// Derives `ConfigSection` for a type nested in a configuration, so fields
+/// marked `#[config(nested)]` can describe it.
+#[proc_macro_derive(ConfigSection, attributes(config))]
+pub fn derive_config_section(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    if let Data::Union(data) = &input.data {
+        return syn::Error::new(
+            data.union_token.span(),
+            "ConfigSection can't be derived for unions",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let schema = match schema_expr(&input) {
+        Ok(schema) => schema,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    TokenStream::from(quote! {
+        impl #impl_generics mtc_config::ConfigSection for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn section_schema(
+                __defaults: Option<&mtc_config::serde_json::Value>,
+            ) -> mtc_config::ConfigSchema {
+                #schema
+            }
+        }
+    })
+}
 
 #[proc_macro_derive(Configuration, attributes(config))]
 pub fn derive_configuration(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
 
+    // Named structs, newtype wrappers and enums are supported
+    match &input.data {
+        Data::Union(data) => {
+            return syn::Error::new(
+                data.union_token.span(),
+                "Configuration can't be derived for unions",
+            )
+            .to_compile_error()
+            .into();
+        }
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() != 1 => {
+                return syn::Error::new(
+                    fields.span(),
+                    "tuple structs must be newtype wrappers with exactly one field, other tuple structs serialize as a sequence that most formats can't store",
+                )
+                .to_compile_error()
+                .into();
+            }
+            _ => {}
+        },
+        Data::Enum(_) => {}
+    }
+
     // Parse attributes for configuration options
     let mut config_name: Option<String> = None;
-    let mut format: Option<String> = None;
+    let mut format: Option<(Ident, LitStr)> = None;
+    let mut migrate = false;
+    let mut multi = false;
+    let mut section: Option<LitStr> = None;
+    let mut file: Option<LitStr> = None;
+    let mut history: Option<usize> = None;
+    let mut global: Option<proc_macro2::Span> = None;
+    let mut default_file: Option<(Ident, LitStr)> = None;
+    let mut encrypted: Option<proc_macro2::Span> = None;
+    let mut integrity: Option<proc_macro2::Span> = None;
+    let mut env_prefix: Option<String> = None;
+    let mut remote: Option<LitStr> = None;
+    let mut codec: Option<LitStr> = None;
+    // Every error is collected so all of them are reported in one compile
+    let mut errors: Option<syn::Error> = None;
+    let mut push_error = |err: syn::Error| match errors.as_mut() {
+        Some(errors) => errors.combine(err),
+        None => errors = Some(err),
+    };
+    let mut seen: Vec<String> = Vec::new();
     for attr in &input.attrs {
         if attr.path().is_ident("config") {
-            let _ = attr.parse_nested_meta(|meta| {
+            let parsed = attr.parse_nested_meta(|meta| {
+                let key = meta
+                    .path
+                    .get_ident()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_default();
+                if KNOWN_KEYS.contains(&key.as_str()) {
+                    if seen.contains(&key) {
+                        return Err(meta.error(format!("duplicate config attribute `{key}`")));
+                    }
+                    seen.push(key.clone());
+                }
+
                 if meta.path.is_ident("name") {
                     let value = meta.value()?;
                     let s: LitStr = value.parse()?;
+                    if s.value().is_empty() {
+                        return Err(syn::Error::new(s.span(), "config name must not be empty"));
+                    }
                     config_name = Some(s.value());
                     Ok(())
                 } else if meta.path.is_ident("format") {
                     let value = meta.value()?;
                     let s: LitStr = value.parse()?;
-                    let v = s.value();
-                    format = match v.as_str() {
-                        "json" => Some("json".to_string()),
-                        "toml" => Some("toml".to_string()),
-                        "yaml" => Some("yaml".to_string()),
-                        _ => None,
-                    };
-
+                    let variant = format_variant(&s.value()).ok_or_else(|| {
+                        syn::Error::new(
+                            s.span(),
+                            format!(
+                                "unknown config format `{}`, expected one of: {KNOWN_FORMATS}{}",
+                                s.value(),
+                                suggestion(&s.value(), KNOWN_FORMATS.split(", "))
+                            ),
+                        )
+                    })?;
+                    format = Some((Ident::new(variant, s.span()), s));
+                    Ok(())
+                } else if meta.path.is_ident("migrate") {
+                    migrate = true;
+                    Ok(())
+                } else if meta.path.is_ident("multi") {
+                    multi = true;
+                    Ok(())
+                } else if meta.path.is_ident("section")
+                    || meta.path.is_ident("file")
+                    || meta.path.is_ident("codec")
+                {
+                    let value = meta.value()?;
+                    let s: LitStr = value.parse()?;
+                    let valid = !s.value().is_empty()
+                        && s.value()
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                    if !valid {
+                        return Err(syn::Error::new(
+                            s.span(),
+                            format!("{key} must only contain ASCII letters, digits, `_` and `-`"),
+                        ));
+                    }
+                    match key.as_str() {
+                        "section" => section = Some(s),
+                        "file" => file = Some(s),
+                        _ if format_variant(&s.value()).is_some() => {
+                            return Err(syn::Error::new(
+                                s.span(),
+                                format!(
+                                    "`{}` is a built-in format, use `format = \"{}\"`",
+                                    s.value(),
+                                    s.value()
+                                ),
+                            ));
+                        }
+                        _ => codec = Some(s),
+                    }
+                    Ok(())
+                } else if meta.path.is_ident("encrypted") {
+                    encrypted = Some(meta.path.span());
+                    Ok(())
+                } else if meta.path.is_ident("integrity") {
+                    integrity = Some(meta.path.span());
+                    Ok(())
+                } else if meta.path.is_ident("env_prefix") {
+                    let value = meta.value()?;
+                    let s: LitStr = value.parse()?;
+                    let valid = s
+                        .value()
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if !valid {
+                        return Err(syn::Error::new(
+                            s.span(),
+                            "env prefix must only contain ASCII letters, digits and `_`",
+                        ));
+                    }
+                    // An empty prefix would apply every variable of the process
+                    if s.value().trim_matches('_').is_empty() {
+                        return Err(syn::Error::new(
+                            s.span(),
+                            "env prefix must contain a letter or digit",
+                        ));
+                    }
+                    env_prefix = Some(s.value());
+                    Ok(())
+                } else if meta.path.is_ident("remote") {
+                    let value = meta.value()?;
+                    let s: LitStr = value.parse()?;
+                    let url = s.value();
+                    if !["http://", "https://", "$"].iter().any(|start| url.starts_with(start)) {
+                        return Err(syn::Error::new(
+                            s.span(),
+                            "remote URL must start with `http://`, `https://` or an environment variable",
+                        ));
+                    }
+                    remote = Some(s);
+                    Ok(())
+                } else if meta.path.is_ident("global") {
+                    global = Some(meta.path.span());
+                    Ok(())
+                } else if meta.path.is_ident("default_file") {
+                    let value = meta.value()?;
+                    let s: LitStr = value.parse()?;
+                    let path = s.value();
+                    let extension = std::path::Path::new(&path)
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let variant = format_variant(&extension).ok_or_else(|| {
+                        syn::Error::new(
+                            s.span(),
+                            format!(
+                                "can't tell the format of default file `{path}` from its extension, expected one of: {KNOWN_FORMATS}"
+                            ),
+                        )
+                    })?;
+                    default_file = Some((Ident::new(variant, s.span()), s));
+                    Ok(())
+                } else if meta.path.is_ident("history") {
+                    let value = meta.value()?;
+                    let n: LitInt = value.parse()?;
+                    history = Some(n.base10_parse()?);
                     Ok(())
                 } else {
-                    Err(meta.error("unsupported repr"))
+                    let path = meta.path.clone();
+                    // Skip the value, if any, so parsing continues after it
+                    if meta.input.peek(syn::Token![=]) {
+                        let _: syn::Expr = meta.value()?.parse()?;
+                    }
+                    Err(syn::Error::new(
+                        path.span(),
+                        format!(
+                            "unknown config attribute `{key}`, expected one of: {}{}",
+                            KNOWN_KEYS.join(", "),
+                            suggestion(&key, KNOWN_KEYS.iter().copied())
+                        ),
+                    ))
                 }
             });
+            if let Err(err) = parsed {
+                push_error(err);
+            }
+        }
+    }
+    if let (Some(codec), Some(_)) = (&codec, &format) {
+        push_error(syn::Error::new(
+            codec.span(),
+            "`codec` can't be combined with `format`",
+        ));
+    }
+    // The remote cache is kept in plaintext next to the configuration file
+    if let (Some(remote), Some(_)) = (&remote, &encrypted) {
+        push_error(syn::Error::new(
+            remote.span(),
+            "`remote` can't be combined with `encrypted`",
+        ));
+    }
+    // The shared file is read and written as a whole by every type in it
+    match (&section, &file) {
+        (None, Some(file)) => {
+            push_error(syn::Error::new(file.span(), "`file` requires `section`"));
+        }
+        (Some(section), _) => {
+            let conflicts: Vec<&str> = [
+                ("migrate", migrate),
+                ("multi", multi),
+                ("encrypted", encrypted.is_some()),
+                ("integrity", integrity.is_some()),
+            ]
+            .into_iter()
+            .filter_map(|(key, set)| set.then_some(key))
+            .collect();
+            if !conflicts.is_empty() {
+                push_error(syn::Error::new(
+                    section.span(),
+                    format!(
+                        "`section` can't be combined with `{}`",
+                        conflicts.join("`, `")
+                    ),
+                ));
+            }
         }
+        (None, None) => {}
+    }
+    if let Some(errors) = errors {
+        return errors.to_compile_error().into();
     }
 
+    // Describe the fields for the runtime schema
+    let schema = match schema_expr(&input) {
+        Ok(schema) => schema,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let schema_impl = quote! {
+        #[allow(unused_variables)]
+        fn schema() -> mtc_config::ConfigSchema {
+            let __defaults = mtc_config::ConfigSchema::defaults_of(&<Self as ::core::default::Default>::default());
+            let __defaults = Some(&__defaults);
+            #schema
+        }
+    };
+
     // Generate config_name implementation
     let config_name_impl = if let Some(name_str) = config_name {
         quote! {
@@ -45,30 +728,288 @@ pub fn derive_configuration(input: TokenStream) -> TokenStream {
             }
         }
     } else {
+        let name_str = to_snake_case(&name.to_string());
         quote! {
             fn config_name() -> String {
-                stringify!(#name).to_lowercase()
+                String::from(#name_str)
             }
         }
     };
 
     // Generate format implementation if specified
-    let format_impl = if let Some(format_tokens) = format {
+    let format_impl = if let Some((variant, _)) = &format {
         quote! {
             fn format() -> mtc_config::ConfigFormat {
-                #format_tokens.into()
+                mtc_config::ConfigFormat::#variant
+            }
+        }
+    } else if let Some(codec) = &codec {
+        quote! {
+            fn format() -> mtc_config::ConfigFormat {
+                mtc_config::ConfigFormat::Custom(#codec)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Fail the build if a format's cargo feature is disabled in mtc-config
+    let format_check = format
+        .iter()
+        .map(|format| ("config format", format))
+        .chain(default_file.iter().map(|file| ("default file", file)))
+        .map(|(what, (variant, literal))| {
+            let message = format!(
+                "{what} `{}` requires the `{}` feature of mtc-config",
+                literal.value(),
+                format_feature(&variant.to_string())
+            );
+            quote_spanned! {literal.span()=>
+                const _: () = ::core::assert!(
+                    mtc_config::ConfigFormat::#variant.is_enabled(),
+                    #message
+                );
+            }
+        })
+        .collect::<proc_macro2::TokenStream>();
+
+    // Embed the default file and check in tests that it still deserializes
+    let (default_file_impl, default_file_test) = if let Some((variant, path)) = &default_file {
+        let test = if input.generics.params.is_empty() {
+            let test_name = Ident::new(
+                &format!(
+                    "{}_default_file_deserializes",
+                    to_snake_case(&name.to_string())
+                ),
+                path.span(),
+            );
+            quote! {
+                #[cfg(test)]
+                #[test]
+                #[allow(non_snake_case)]
+                fn #test_name() {
+                    if let Err(e) = <#name as mtc_config::Configuration>::embedded_default() {
+                        panic!("default file `{}` of {} is invalid: {}", #path, stringify!(#name), e);
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+        let implementation = quote! {
+            fn default_file() -> Option<(mtc_config::ConfigFormat, &'static str)> {
+                Some((
+                    mtc_config::ConfigFormat::#variant,
+                    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #path)),
+                ))
+            }
+        };
+        (implementation, test)
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // Generate migrate_on_load implementation if requested
+    let migrate_impl = if migrate {
+        quote! {
+            fn migrate_on_load() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate multi implementation if requested
+    let multi_impl = if multi {
+        quote! {
+            fn multi() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate the shared file implementation if a section is specified
+    let section_impl = if let Some(section) = section {
+        let file_impl = file.map(|file| {
+            quote! {
+                fn file_name() -> String {
+                    String::from(#file)
+                }
+            }
+        });
+        quote! {
+            fn section() -> Option<String> {
+                Some(String::from(#section))
+            }
+            #file_impl
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate encrypted implementation if requested, the feature must be enabled
+    let encrypted_impl = if let Some(span) = encrypted {
+        let check = quote_spanned! {span=>
+            const _: () = ::core::assert!(
+                mtc_config::encryption::is_enabled(),
+                "`encrypted` requires the `encryption` feature of mtc-config"
+            );
+        };
+        quote! {
+            fn encrypted() -> bool {
+                #check
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate integrity implementation if requested, the feature must be enabled
+    let integrity_impl = if let Some(span) = integrity {
+        let check = quote_spanned! {span=>
+            const _: () = ::core::assert!(
+                mtc_config::integrity::is_enabled(),
+                "`integrity` requires the `integrity` feature of mtc-config"
+            );
+        };
+        quote! {
+            fn integrity() -> bool {
+                #check
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate env_prefix implementation if specified
+    let env_prefix_impl = if let Some(prefix) = env_prefix {
+        quote! {
+            fn env_prefix() -> Option<String> {
+                Some(String::from(#prefix))
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate remote_url implementation if specified, the feature must be enabled
+    let remote_impl = if let Some(url) = remote {
+        let check = quote_spanned! {url.span()=>
+            const _: () = ::core::assert!(
+                mtc_config::remote::is_enabled(),
+                "`remote` requires the `remote` feature of mtc-config"
+            );
+        };
+        quote! {
+            fn remote_url() -> Option<String> {
+                #check
+                Some(String::from(#url))
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate history_limit implementation if specified
+    let history_impl = if let Some(limit) = history {
+        quote! {
+            fn history_limit() -> usize {
+                #limit
             }
         }
     } else {
         quote! {}
     };
 
+    // Generate the process-wide instance accessors if requested
+    let global_impl = if let Some(span) = global {
+        if !input.generics.params.is_empty() {
+            return syn::Error::new(
+                span,
+                "`global` is not supported for generic configuration types",
+            )
+            .to_compile_error()
+            .into();
+        }
+        quote! {
+            #[allow(dead_code)]
+            impl #name {
+                fn __mtc_config_global() -> &'static mtc_config::GlobalConfig<#name> {
+                    static GLOBAL: mtc_config::GlobalConfig<#name> = mtc_config::GlobalConfig::new();
+                    &GLOBAL
+                }
+
+                /// Gets the process-wide configuration, loading it on first use.
+                pub fn global() -> ::std::sync::Arc<Self> {
+                    Self::__mtc_config_global().get()
+                }
+
+                /// Reloads the process-wide configuration from disk.
+                pub fn reload_global() -> Result<::std::sync::Arc<Self>, mtc_config::ConfigError> {
+                    Self::__mtc_config_global().reload()
+                }
+
+                /// Replaces the process-wide configuration until the guard is dropped.
+                #[cfg(test)]
+                pub fn override_global(config: Self) -> mtc_config::GlobalOverride<'static, Self> {
+                    Self::__mtc_config_global().override_with(config)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Register the type, generic types have no single type to register
+    let register_impl = if input.generics.params.is_empty() {
+        quote! {
+            mtc_config::inventory::submit! {
+                mtc_config::RegisteredConfig::of::<#name>(
+                    concat!(module_path!(), "::", stringify!(#name))
+                )
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generic types need the serde and Default supertraits proven for the concrete type
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    if !input.generics.params.is_empty() {
+        where_clause.predicates.push(parse_quote! {
+            #name #ty_generics: mtc_config::Serialize
+                + for<'de> mtc_config::Deserialize<'de>
+                + ::core::default::Default
+        });
+    }
+
     // Generate the implementation
     let expanded = quote! {
-        impl mtc_config::Configuration for #name {
+        impl #impl_generics mtc_config::Configuration for #name #ty_generics #where_clause {
             #config_name_impl
             #format_impl
+            #migrate_impl
+            #multi_impl
+            #section_impl
+            #history_impl
+            #encrypted_impl
+            #integrity_impl
+            #env_prefix_impl
+            #remote_impl
+            #schema_impl
+            #default_file_impl
         }
+
+        #format_check
+        #default_file_test
+        #global_impl
+        #register_impl
     };
 
     TokenStream::from(expanded)
@@ -0,0 +1,152 @@
+/*
+ * C API of mtc-config, built with the `ffi` feature.
+ *
+ * Link against the `mtc_config` static or dynamic library. Functions
+ * returning `int` return 0 on success and -1 on failure, functions returning
+ * a pointer return NULL on failure; `mtc_config_last_error()` describes the
+ * last failure on the calling thread.
+ *
+ * Paths are dotted, with sequence items as `[index]`: "server.port",
+ * "authors[0]".
+ */
+
+#ifndef MTC_CONFIG_H
+#define MTC_CONFIG_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * A configuration file loaded through the C ABI.
+ */
+typedef struct MtcConfig MtcConfig;
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Loads the configuration file `<name>.<ext>` from the configs directory.
+ *
+ * `format` is a format name like `"toml"`; if null, the file is found like
+ * `load()` finds it: in the declared format of the registered type named
+ * `name`, then with any fallback extension such as `.yml` or the one of a
+ * registered codec. Returns null on failure.
+ *
+ * Configurations declared with `section` are tables of a file shared with
+ * other types and can't be opened by their own name: open the shared file,
+ * e.g. `workspace`, and use paths in their table, e.g. `mkbook.title`.
+ *
+ * # Safety
+ * `name` must point to a valid NUL-terminated string and `format` must be
+ * null or point to one. The returned handle must be released with
+ * [`mtc_config_free`].
+ */
+struct MtcConfig *mtc_config_load(const char *name, const char *format);
+
+/**
+ * Like [`mtc_config_load`], but starts from an empty configuration if the
+ * file does not exist yet.
+ *
+ * # Safety
+ * Same as [`mtc_config_load`].
+ */
+struct MtcConfig *mtc_config_open(const char *name, const char *format);
+
+/**
+ * Releases a handle; null is ignored.
+ *
+ * # Safety
+ * `config` must be null or a handle returned by this library that was not
+ * released yet.
+ */
+void mtc_config_free(struct MtcConfig *config);
+
+/**
+ * Gets the string at `path`. On success `*out` receives a new string that
+ * must be released with [`mtc_config_string_free`].
+ *
+ * # Safety
+ * `config` must be a live handle, `path` a valid NUL-terminated string and
+ * `out` valid for writes.
+ */
+int mtc_config_get_string(const struct MtcConfig *config, const char *path, char **out);
+
+/**
+ * Gets the integer at `path` into `*out`.
+ *
+ * # Safety
+ * `config` must be a live handle, `path` a valid NUL-terminated string and
+ * `out` valid for writes.
+ */
+int mtc_config_get_int(const struct MtcConfig *config, const char *path, int64_t *out);
+
+/**
+ * Gets the boolean at `path` into `*out`.
+ *
+ * # Safety
+ * `config` must be a live handle, `path` a valid NUL-terminated string and
+ * `out` valid for writes.
+ */
+int mtc_config_get_bool(const struct MtcConfig *config, const char *path, bool *out);
+
+/**
+ * Sets the string at `path`, creating missing tables on the way.
+ *
+ * # Safety
+ * `config` must be a live handle, `path` and `value` valid NUL-terminated
+ * strings.
+ */
+int mtc_config_set_string(struct MtcConfig *config, const char *path, const char *value);
+
+/**
+ * Sets the integer at `path`, creating missing tables on the way.
+ *
+ * # Safety
+ * `config` must be a live handle and `path` a valid NUL-terminated string.
+ */
+int mtc_config_set_int(struct MtcConfig *config, const char *path, int64_t value);
+
+/**
+ * Sets the boolean at `path`, creating missing tables on the way.
+ *
+ * # Safety
+ * `config` must be a live handle and `path` a valid NUL-terminated string.
+ */
+int mtc_config_set_bool(struct MtcConfig *config, const char *path, bool value);
+
+/**
+ * Writes the configuration back to its file, creating the configs directory
+ * if needed. The file is replaced atomically and, for a registered type
+ * keeping backups, the previous version is backed up like `save()` does.
+ *
+ * # Safety
+ * `config` must be a live handle.
+ */
+int mtc_config_save(const struct MtcConfig *config);
+
+/**
+ * Gets the message of the last failure on the calling thread, or null if
+ * nothing failed yet. The string stays valid until the next failing call on
+ * the same thread and must not be released.
+ */
+const char *mtc_config_last_error(void);
+
+/**
+ * Releases a string returned by [`mtc_config_get_string`]; null is ignored.
+ *
+ * # Safety
+ * `string` must be null or a string returned by this library that was not
+ * released yet.
+ */
+void mtc_config_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MTC_CONFIG_H */
@@ -0,0 +1,158 @@
+//! Structural diff between configuration values.
+//!
+//! Both sides are serialized into a `serde_json::Value` tree and compared key by
+//! key, so the result does not depend on the on-disk format. Paths are dotted
+//! (`server.port`) with sequence items as `[index]` (`authors[0]`). A `null`
+//! value is treated the same as a missing one, and added or removed tables
+//! are reported leaf by leaf.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::ConfigError;
+
+/// What happened to a value between the old and the new configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// The value is only present in the new configuration.
+    Added,
+    /// The value is only present in the old configuration.
+    Removed,
+    /// The value is present in both configurations but differs.
+    Changed,
+}
+
+/// A single difference between two configurations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    /// The dotted path of the value, e.g. `server.port` or `authors[0]`.
+    pub path: String,
+    /// What happened to the value.
+    pub kind: ChangeKind,
+    /// The old value, if there was one.
+    pub old: Option<Value>,
+    /// The new value, if there is one.
+    pub new: Option<Value>,
+}
+
+/// The list of differences between two configurations, ordered by path.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ConfigDiff {
+    /// The individual changes.
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigDiff {
+    /// Compares two serializable values.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If one of the values can't be serialized
+    pub fn between<A: Serialize, B: Serialize>(old: &A, new: &B) -> Result<Self, ConfigError> {
+        let to_value = |v: Result<Value, serde_json::Error>| {
+            v.map_err(|e| ConfigError::Serialization(e.to_string()))
+        };
+        Ok(Self::between_values(
+            &to_value(serde_json::to_value(old))?,
+            &to_value(serde_json::to_value(new))?,
+        ))
+    }
+
+    /// Compares two already serialized value trees.
+    pub fn between_values(old: &Value, new: &Value) -> Self {
+        let mut diff = ConfigDiff::default();
+        diff.compare(String::new(), old, new);
+        diff
+    }
+
+    /// Checks whether the two configurations are equal.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Renders the diff as one line per change: `+ path = value` for added,
+    /// `- path = value` for removed and `~ path: old -> new` for changed values.
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Renders the diff as a pretty-printed JSON array of changes.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the diff can't be serialized
+    pub fn to_json(&self) -> Result<String, ConfigError> {
+        serde_json::to_string_pretty(self).map_err(|e| ConfigError::Serialization(e.to_string()))
+    }
+
+    fn compare(&mut self, path: String, old: &Value, new: &Value) {
+        match (old, new) {
+            (Value::Null, Value::Null) => {}
+            // Report the leaves of added or removed tables and sequences
+            (Value::Null, Value::Object(_)) => self.compare(path, &Value::Object(Map::new()), new),
+            (Value::Object(_), Value::Null) => self.compare(path, old, &Value::Object(Map::new())),
+            (Value::Null, Value::Array(_)) => self.compare(path, &Value::Array(Vec::new()), new),
+            (Value::Array(_), Value::Null) => self.compare(path, old, &Value::Array(Vec::new())),
+            (Value::Object(a), Value::Object(b)) => {
+                let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    let old = a.get(key).unwrap_or(&Value::Null);
+                    let new = b.get(key).unwrap_or(&Value::Null);
+                    self.compare(join(&path, key), old, new);
+                }
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                for index in 0..a.len().max(b.len()) {
+                    let old = a.get(index).unwrap_or(&Value::Null);
+                    let new = b.get(index).unwrap_or(&Value::Null);
+                    self.compare(format!("{path}[{index}]"), old, new);
+                }
+            }
+            (Value::Null, new) => self.push(path, ChangeKind::Added, None, Some(new)),
+            (old, Value::Null) => self.push(path, ChangeKind::Removed, Some(old), None),
+            (old, new) if old != new => self.push(path, ChangeKind::Changed, Some(old), Some(new)),
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, path: String, kind: ChangeKind, old: Option<&Value>, new: Option<&Value>) {
+        self.changes.push(ConfigChange {
+            path,
+            kind,
+            old: old.cloned(),
+            new: new.cloned(),
+        });
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| value.as_ref().map(Value::to_string).unwrap_or_default();
+        for change in &self.changes {
+            match change.kind {
+                ChangeKind::Added => writeln!(f, "+ {} = {}", change.path, show(&change.new))?,
+                ChangeKind::Removed => writeln!(f, "- {} = {}", change.path, show(&change.old))?,
+                ChangeKind::Changed => writeln!(
+                    f,
+                    "~ {}: {} -> {}",
+                    change.path,
+                    show(&change.old),
+                    show(&change.new)
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Interactive terminal editor driven by the schema.
+//!
+//! [`edit`] walks the fields of a configuration, nested sections included,
+//! and prompts for each one with its doc comment, type, validation rules,
+//! current value and default:
+//!
+//! ```text
+//! server.port (u16): The port to listen on
+//!   rules: min = 1, max = 65535
+//!   default: 8080
+//!   current: 9000
+//! >
+//! ```
+//!
+//! Fields of the [`types`](crate::types) value types also show the text they
+//! expect on an `expects:` line, e.g. `expects: a duration like 30s`.
+//!
+//! An empty answer keeps the current value and `~` resets it to the default.
+//! Strings are taken as typed, other values are parsed as JSON (`true`,
+//! `42`, `["a", "b"]`). Answers that don't fit the field type or break its
+//! rules are reported and asked again. Secret values are never displayed.
+//! Nothing is written until every field is answered and the changes are
+//! confirmed, then the configuration is saved at once.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+use crate::schema::is_within;
+use crate::{ConfigDiff, ConfigError, ConfigSchema, Configuration, ValidationRule};
+
+/// Answer resetting a field to its default.
+pub const RESET: &str = "~";
+
+/// Edits the configuration `T` on the terminal, see the module docs.
+///
+/// # Returns
+/// The saved configuration, or None if saving was declined.
+///
+/// # Errors
+/// Same as [`edit_with`].
+pub fn edit<T: Configuration>() -> Result<Option<T>, ConfigError> {
+    edit_with::<T>(&mut io::stdin().lock(), &mut io::stdout().lock())
+}
+
+/// Edits the configuration `T`, reading answers from `input` and writing
+/// prompts to `output`.
+///
+/// The saved configuration is loaded, without environment overrides, or the
+/// default one if no file exists yet.
+///
+/// # Returns
+/// The saved configuration, or None if saving was declined.
+///
+/// # Errors
+/// - `ConfigError::Io`: If the input ends before the editor is done, or reading or writing fails
+/// - Any error of [`Configuration::load`] other than `NotFound`, and of [`Configuration::save`]
+pub fn edit_with<T: Configuration>(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<Option<T>, ConfigError> {
+    // Environment overrides are not part of the file, so they are not edited
+    let current = match crate::load_saved::<T>() {
+        Ok(config) => config,
+        Err(ConfigError::NotFound(_)) => T::embedded_default()?.unwrap_or_default(),
+        Err(e) => return Err(e),
+    };
+    let to_value = |config: &T| {
+        serde_json::to_value(config).map_err(|e| ConfigError::Serialization(e.to_string()))
+    };
+    let original = to_value(&current)?;
+    let defaults = to_value(&T::default())?;
+    let schema = T::schema();
+
+    let mut editor = Editor::<T, _, _> {
+        input,
+        output,
+        value: original.clone(),
+        defaults,
+        config: current,
+    };
+    writeln!(editor.output, "Editing `{}`", T::config_name()).map_err(ConfigError::Io)?;
+    if schema.fields.is_empty() {
+        editor.field(&[], schema.type_name, schema.doc, None, &[], false)?;
+    } else {
+        editor.section(&schema, &mut Vec::new())?;
+    }
+
+    let diff = ConfigDiff::between_values(&original, &editor.value);
+    if diff.is_empty() {
+        writeln!(editor.output, "No changes").map_err(ConfigError::Io)?;
+        return Ok(None);
+    }
+    let secrets = schema.secret_paths();
+    writeln!(editor.output, "\nChanges:").map_err(ConfigError::Io)?;
+    for change in &diff.changes {
+        if secrets.iter().any(|secret| is_within(&change.path, secret)) {
+            writeln!(editor.output, "~ {}: *secret*", change.path)
+        } else {
+            writeln!(
+                editor.output,
+                "{}",
+                ConfigDiff {
+                    changes: vec![change.clone()]
+                }
+                .to_text()
+                .trim_end()
+            )
+        }
+        .map_err(ConfigError::Io)?;
+    }
+    if !editor.confirm("Save?")? {
+        return Ok(None);
+    }
+    editor.config.save()?;
+    Ok(Some(editor.config))
+}
+
+struct Editor<'a, T, R, W> {
+    input: &'a mut R,
+    output: &'a mut W,
+    /// The edited configuration as a value tree.
+    value: Value,
+    defaults: Value,
+    /// The edited configuration, kept in sync with `value`.
+    config: T,
+}
+
+impl<T: Configuration, R: BufRead, W: Write> Editor<'_, T, R, W> {
+    fn section(
+        &mut self,
+        schema: &ConfigSchema,
+        path: &mut Vec<&'static str>,
+    ) -> Result<(), ConfigError> {
+        for field in &schema.fields {
+            path.push(field.name);
+            match &field.nested {
+                Some(nested) if !nested.fields.is_empty() => self.section(nested, path)?,
+                _ => self.field(
+                    path,
+                    field.type_name,
+                    field.doc,
+                    field.value_format,
+                    &field.rules,
+                    field.secret,
+                )?,
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn field(
+        &mut self,
+        path: &[&str],
+        type_name: &str,
+        doc: &str,
+        value_format: Option<&str>,
+        rules: &[ValidationRule],
+        secret: bool,
+    ) -> Result<(), ConfigError> {
+        let current = lookup(&self.value, path).cloned().unwrap_or(Value::Null);
+        let default = lookup(&self.defaults, path).cloned().unwrap_or(Value::Null);
+        let shown = |value: &Value| match value {
+            Value::Null => "(none)".to_string(),
+            _ if secret => "*secret*".to_string(),
+            value => value.to_string(),
+        };
+
+        let name = if path.is_empty() {
+            "value".to_string()
+        } else {
+            path.join(".")
+        };
+        let mut prompt = format!("\n{name} ({type_name})");
+        if let Some(doc) = doc.lines().next().filter(|doc| !doc.is_empty()) {
+            prompt.push_str(&format!(": {doc}"));
+        }
+        if let Some(value_format) = value_format {
+            prompt.push_str(&format!("\n  expects: a {value_format}"));
+        }
+        if !rules.is_empty() {
+            let rules: Vec<String> = rules.iter().map(ToString::to_string).collect();
+            prompt.push_str(&format!("\n  rules: {}", rules.join(", ")));
+        }
+        prompt.push_str(&format!("\n  default: {}", shown(&default)));
+        prompt.push_str(&format!("\n  current: {}", shown(&current)));
+        writeln!(self.output, "{prompt}").map_err(ConfigError::Io)?;
+
+        loop {
+            let answer = self.ask("> ")?;
+            let candidates = match answer.as_str() {
+                "" => return Ok(()),
+                RESET => vec![default.clone()],
+                answer => parse_answer(answer, &current, &default),
+            };
+            match self.try_set(path, candidates, rules) {
+                Ok(()) => return Ok(()),
+                Err(message) => writeln!(self.output, "  {message}").map_err(ConfigError::Io)?,
+            }
+        }
+    }
+
+    /// Sets the first candidate value that deserializes and passes the rules.
+    fn try_set(
+        &mut self,
+        path: &[&str],
+        candidates: Vec<Value>,
+        rules: &[ValidationRule],
+    ) -> Result<(), String> {
+        let mut error = String::new();
+        for candidate in candidates {
+            if let Some(broken) = rules.iter().find_map(|rule| rule.check(&candidate).err()) {
+                error = broken;
+                continue;
+            }
+            let mut value = self.value.clone();
+            match lookup_mut(&mut value, path) {
+                Some(node) => *node = candidate,
+                None => return Err("can't set a value here".to_string()),
+            }
+            match serde_json::from_value::<T>(value.clone()) {
+                Ok(config) => {
+                    self.value = value;
+                    self.config = config;
+                    return Ok(());
+                }
+                Err(e) => error = e.to_string(),
+            }
+        }
+        Err(error)
+    }
+
+    fn ask(&mut self, prompt: &str) -> Result<String, ConfigError> {
+        write!(self.output, "{prompt}")
+            .and_then(|()| self.output.flush())
+            .map_err(ConfigError::Io)?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line).map_err(ConfigError::Io)? == 0 {
+            return Err(ConfigError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input ended before the editor was done, nothing was saved",
+            )));
+        }
+        Ok(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool, ConfigError> {
+        loop {
+            match self
+                .ask(&format!("{question} [Y/n] "))?
+                .trim()
+                .to_lowercase()
+                .as_str()
+            {
+                "" | "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lists the values an answer may mean, most likely first.
+///
+/// Strings are taken as typed when the current or default value is a
+/// string; otherwise the answer is parsed as JSON, falling back to a string
+/// when there is no value to tell the type from.
+fn parse_answer(answer: &str, current: &Value, default: &Value) -> Vec<Value> {
+    let reference = if current.is_null() { default } else { current };
+    let parsed = serde_json::from_str::<Value>(answer);
+    match (reference, parsed) {
+        (Value::String(_), _) => vec![Value::String(answer.to_string())],
+        (Value::Null, Ok(parsed)) => vec![parsed, Value::String(answer.to_string())],
+        (Value::Null, Err(_)) => vec![Value::String(answer.to_string())],
+        (_, Ok(parsed)) => vec![parsed],
+        // Let deserialization explain what was expected
+        (_, Err(_)) => vec![Value::String(answer.to_string())],
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |node, key| node.get(key))
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, path: &[&str]) -> Option<&'a mut Value> {
+    path.iter().try_fold(value, |node, key| match node {
+        Value::Null => {
+            *node = Value::Object(Default::default());
+            node.as_object_mut()
+                .map(|map| map.entry(*key).or_insert(Value::Null))
+        }
+        Value::Object(map) => Some(map.entry(*key).or_insert(Value::Null)),
+        _ => None,
+    })
+}
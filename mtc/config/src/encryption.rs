@@ -0,0 +1,125 @@
+//! Encryption of configuration files at rest.
+//!
+//! Configurations declared with `#[config(encrypted)]` are serialized as usual,
+//! then sealed with ChaCha20-Poly1305 before `save()` writes them, and opened
+//! again by `load()`. The file keeps its usual path and extension but holds an
+//! envelope instead of plain text:
+//!
+//! ```text
+//! mtc-config:encrypted:v1:chacha20poly1305
+//! <hex nonce><hex ciphertext>
+//! ```
+//!
+//! The configuration name is authenticated along with the content, so an
+//! envelope copied over another configuration fails to open. The key is 32
+//! bytes written as 64 hex characters (`openssl rand -hex 32`), read from the
+//! [`KEY_ENV`] environment variable or else from the file named by
+//! [`KEY_FILE_ENV`]. Requires the `encryption` feature.
+
+use std::fs;
+
+use crate::{ConfigError, hex};
+
+/// Environment variable holding the hex encoded key.
+pub const KEY_ENV: &str = "MTC_CONFIG_KEY";
+
+/// Environment variable naming a file that holds the hex encoded key.
+pub const KEY_FILE_ENV: &str = "MTC_CONFIG_KEY_FILE";
+
+/// First line of an encrypted configuration file.
+pub const HEADER: &str = "mtc-config:encrypted:v1:chacha20poly1305";
+
+/// Checks whether the `encryption` feature is enabled.
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "encryption")
+}
+
+/// Checks whether file content is an encrypted envelope.
+pub fn is_sealed(content: &str) -> bool {
+    content.lines().next() == Some(HEADER)
+}
+
+/// Reads the key from [`KEY_ENV`] or the file named by [`KEY_FILE_ENV`].
+///
+/// # Errors
+/// - `ConfigError::Encryption`: If neither is set or the key is not 64 hex characters
+/// - `ConfigError::Io`: If the key file can't be read
+pub fn load_key() -> Result<[u8; 32], ConfigError> {
+    let key = match (crate::env_var(KEY_ENV), crate::env_var(KEY_FILE_ENV)) {
+        (Some(key), _) => key,
+        (None, Some(path)) => fs::read_to_string(path).map_err(ConfigError::Io)?,
+        _ => {
+            return Err(ConfigError::Encryption(format!(
+                "no key, set {KEY_ENV} or {KEY_FILE_ENV}"
+            )));
+        }
+    };
+    hex::decode(key.trim())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| ConfigError::Encryption("the key must be 64 hex characters".to_string()))
+}
+
+/// Encrypts `content` into an envelope bound to the configuration `name`.
+#[cfg(feature = "encryption")]
+pub(crate) fn seal(content: &str, key: &[u8; 32], name: &str) -> Result<String, ConfigError> {
+    use chacha20poly1305::ChaCha20Poly1305;
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload {
+        msg: content.as_bytes(),
+        aad: name.as_bytes(),
+    };
+    let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|_| ConfigError::Encryption("encryption failed".to_string()))?;
+    Ok(format!(
+        "{HEADER}\n{}{}\n",
+        hex::encode(&nonce),
+        hex::encode(&ciphertext)
+    ))
+}
+
+/// Decrypts an envelope written by [`seal`] for the configuration `name`.
+#[cfg(feature = "encryption")]
+pub(crate) fn open(content: &str, key: &[u8; 32], name: &str) -> Result<String, ConfigError> {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+    let body: String = content.lines().skip(1).collect();
+    let bytes = hex::decode(body.trim())
+        .ok_or_else(|| ConfigError::Encryption("invalid hex in encrypted content".to_string()))?;
+    if bytes.len() < 12 {
+        return Err(ConfigError::Encryption(
+            "truncated encrypted content".to_string(),
+        ));
+    }
+    let (nonce, ciphertext) = bytes.split_at(12);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: name.as_bytes(),
+    };
+    let plain = ChaCha20Poly1305::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| {
+            ConfigError::Encryption(format!(
+                "can't decrypt `{name}`, wrong key or tampered content"
+            ))
+        })?;
+    String::from_utf8(plain).map_err(|e| ConfigError::Encryption(e.to_string()))
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn seal(_content: &str, _key: &[u8; 32], _name: &str) -> Result<String, ConfigError> {
+    Err(ConfigError::Encryption(
+        "encrypting requires the `encryption` feature".to_string(),
+    ))
+}
+
+#[cfg(not(feature = "encryption"))]
+pub(crate) fn open(_content: &str, _key: &[u8; 32], _name: &str) -> Result<String, ConfigError> {
+    Err(ConfigError::Encryption(
+        "decrypting requires the `encryption` feature".to_string(),
+    ))
+}
@@ -0,0 +1,208 @@
+//! Environment variable overrides and exports.
+//!
+//! A configuration is flattened into one variable per value, named after its
+//! path: the prefix, then the keys in uppercase separated by `__`, so
+//! `server.port` with the prefix `APP` becomes `APP_SERVER__PORT`. Strings
+//! are written as is, sequences as JSON and `null` values are left out.
+//!
+//! [`Configuration::with_env`](crate::Configuration::with_env) reads the same
+//! names back, taking the type of each value from the configuration it is
+//! applied to, so `to_env` followed by `with_env` gives back the same
+//! configuration. Types declaring `#[config(env_prefix = "APP")]` have their
+//! overrides applied by `load()`.
+//!
+//! [`export`] writes the variables as a `.env` file, a shell script or a
+//! docker-compose `environment:` block, so ports and hosts only need to be
+//! changed in the configuration file.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::ConfigError;
+use crate::format::dotenv;
+
+/// Separator between the keys of nested tables in variable names.
+pub const SEPARATOR: &str = "__";
+
+/// How [`export`] writes variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnvStyle {
+    /// `KEY=value` lines for a `.env` or docker `env_file`.
+    #[default]
+    Dotenv,
+    /// `export KEY='value'` lines to `source` in a shell.
+    Export,
+    /// An `environment:` block for a docker-compose service.
+    DockerCompose,
+}
+
+impl fmt::Display for EnvStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EnvStyle::Dotenv => "dotenv",
+            EnvStyle::Export => "export",
+            EnvStyle::DockerCompose => "compose",
+        })
+    }
+}
+
+impl FromStr for EnvStyle {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dotenv" | "env" => Ok(EnvStyle::Dotenv),
+            "export" | "shell" | "sh" => Ok(EnvStyle::Export),
+            "compose" | "docker-compose" => Ok(EnvStyle::DockerCompose),
+            _ => Err(ConfigError::InvalidArgument(format!(
+                "unknown env style `{s}`, expected one of: dotenv, export, compose"
+            ))),
+        }
+    }
+}
+
+/// Gets the variable name of the top-level key `key`.
+fn var_name(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_uppercase()
+    } else {
+        let prefix = prefix.trim_end_matches('_').to_uppercase();
+        format!("{prefix}_{}", key.to_uppercase())
+    }
+}
+
+/// Flattens a serialized configuration into `(name, value)` pairs, ordered
+/// by key.
+pub fn flatten(value: &Value, prefix: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    if let Value::Object(map) = value {
+        for (key, value) in map {
+            flatten_into(var_name(prefix, key), value, &mut vars);
+        }
+    }
+    vars
+}
+
+fn flatten_into(name: String, value: &Value, vars: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = format!("{name}{SEPARATOR}{}", key.to_uppercase());
+                flatten_into(name, value, vars);
+            }
+        }
+        Value::String(string) => vars.push((name, string.clone())),
+        other => vars.push((name, other.to_string())),
+    }
+}
+
+/// Applies the variables starting with `prefix` to a serialized configuration.
+///
+/// Keys are matched case-insensitively against the existing ones, and values
+/// are parsed according to the value they replace: strings are taken as is,
+/// anything else is parsed as JSON. Values without an existing one to go by
+/// are parsed as JSON if possible; their paths and raw values are returned so
+/// the caller can retry them as strings if the result doesn't deserialize.
+///
+/// # Errors
+/// - `ConfigError::InvalidArgument`: If the prefix is empty, which would
+///   apply every variable, or a variable goes through a value that is not a table
+/// - `ConfigError::Deserialization`: If a value can't be parsed as the one it replaces
+pub fn apply(
+    value: &mut Value,
+    prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Vec<(Vec<String>, String)>, ConfigError> {
+    if prefix.trim_matches('_').is_empty() {
+        return Err(ConfigError::InvalidArgument(format!(
+            "env prefix `{prefix}` must contain a letter or digit"
+        )));
+    }
+    let mut guessed = Vec::new();
+    let start = var_name(prefix, "");
+    for (name, raw) in vars {
+        let Some(rest) = name.strip_prefix(&start).filter(|rest| !rest.is_empty()) else {
+            continue;
+        };
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+
+        let mut path = Vec::new();
+        let mut node = &mut *value;
+        for segment in rest.split(SEPARATOR) {
+            let Value::Object(map) = node else {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "`{name}` goes through `{}`, which is not a table",
+                    path.join(".")
+                )));
+            };
+            let key = map
+                .keys()
+                .find(|key| key.to_uppercase() == segment)
+                .cloned()
+                .unwrap_or_else(|| segment.to_lowercase());
+            path.push(key.clone());
+            node = map.entry(key).or_insert(Value::Null);
+        }
+
+        *node = match node {
+            Value::String(_) => Value::String(raw),
+            Value::Null => match serde_json::from_str::<Value>(&raw) {
+                Ok(parsed) if !parsed.is_string() => {
+                    guessed.push((path, raw));
+                    parsed
+                }
+                _ => Value::String(raw),
+            },
+            _ => serde_json::from_str(&raw).map_err(|e| {
+                ConfigError::Deserialization(format!("`{name}` is not a valid value: {e}"))
+            })?,
+        };
+    }
+    Ok(guessed)
+}
+
+/// Sets the value at `path` back to the string it was parsed from.
+pub(crate) fn restore_string(value: &mut Value, path: &[String], raw: &str) {
+    if let Some(node) = path
+        .iter()
+        .try_fold(value, |node, key| node.get_mut(key.as_str()))
+    {
+        *node = Value::String(raw.to_string());
+    }
+}
+
+/// Writes variables in the given style.
+///
+/// `.env` lines are written by the dotenv format, which quotes values that
+/// it wouldn't read back as the same string.
+pub fn export(vars: &[(String, String)], style: EnvStyle) -> String {
+    let mut out = String::new();
+    if style == EnvStyle::DockerCompose {
+        out.push_str("environment:\n");
+    }
+    for (name, value) in vars {
+        match style {
+            EnvStyle::Dotenv => {
+                // A string is always a scalar
+                let _ = dotenv::write_line(&mut out, name, &Value::String(value.clone()));
+            }
+            EnvStyle::Export => {
+                out.push_str(&format!(
+                    "export {name}='{}'\n",
+                    value.replace('\'', "'\\''")
+                ));
+            }
+            // A JSON string is a valid YAML double-quoted scalar, `$$` escapes interpolation
+            EnvStyle::DockerCompose => {
+                let quoted = Value::String(value.replace('$', "$$"));
+                out.push_str(&format!("  {name}: {quoted}\n"));
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,530 @@
+//! C ABI for reading and writing configuration files without Rust types.
+//!
+//! Enabled with the `ffi` feature; `include/mtc_config.h` declares every
+//! function below and is generated from this file by cbindgen, see
+//! `cbindgen.toml`. A configuration is loaded into an opaque [`MtcConfig`]
+//! handle holding the parsed value tree, read and modified by dotted path
+//! (`server.port`, `authors[0]`) and written back with [`mtc_config_save`].
+//!
+//! Encrypted files are decrypted with the key from the environment, see
+//! [`crate::encryption`], and encrypted again when saved. Files with a
+//! checksum sidecar are verified when loaded and resealed when saved, see
+//! [`crate::integrity`].
+//!
+//! Functions returning `int` return `0` on success and `-1` on failure.
+//! Functions returning a pointer return null on failure. The reason of the
+//! last failure on the calling thread is available from
+//! [`mtc_config_last_error`].
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    ConfigError, ConfigFormat, check_file_stem, encryption, fallback_formats,
+    find_registered_config, get_configs_dir, history, integrity, read_file, write_sealed_file,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A configuration file loaded through the C ABI.
+pub struct MtcConfig {
+    name: String,
+    path: PathBuf,
+    format: ConfigFormat,
+    value: Value,
+    encrypted: bool,
+    sealed: bool,
+}
+
+fn set_error(message: impl ToString) {
+    let message = message.to_string().replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Runs `f`, recording its error for `mtc_config_last_error` and mapping the
+/// result to the `0` / `-1` convention.
+fn status(f: impl FnOnce() -> Result<(), ConfigError>) -> c_int {
+    match f() {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// Reads a C string argument.
+///
+/// # Safety
+/// `ptr` must be null or point to a valid NUL-terminated string.
+unsafe fn arg<'a>(ptr: *const c_char, what: &str) -> Result<&'a str, ConfigError> {
+    if ptr.is_null() {
+        return Err(ConfigError::InvalidArgument(format!("`{what}` is null")));
+    }
+    // SAFETY: the caller guarantees `ptr` is a valid NUL-terminated string
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| ConfigError::InvalidArgument(format!("`{what}` is not valid UTF-8")))
+}
+
+/// Splits a dotted path into keys and sequence indices.
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment<'_>>, ConfigError> {
+    let invalid = || ConfigError::InvalidArgument(format!("invalid path `{path}`"));
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if key.is_empty() && segments.is_empty() {
+            return Err(invalid());
+        }
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+        while let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']').ok_or_else(invalid)?;
+            segments.push(Segment::Index(inner[..end].parse().map_err(|_| invalid())?));
+            rest = &inner[end + 1..];
+        }
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+impl MtcConfig {
+    fn get(&self, path: &str) -> Result<&Value, ConfigError> {
+        let mut value = &self.value;
+        for segment in parse_path(path)? {
+            value = match segment {
+                Segment::Key(key) => value.get(key),
+                Segment::Index(index) => value.get(index),
+            }
+            .ok_or_else(|| ConfigError::InvalidArgument(format!("no value at `{path}`")))?;
+        }
+        Ok(value)
+    }
+
+    fn set(&mut self, path: &str, new: Value) -> Result<(), ConfigError> {
+        let mut value = &mut self.value;
+        for segment in parse_path(path)? {
+            value = match segment {
+                Segment::Key(key) => {
+                    if !value.is_object() {
+                        *value = Value::Object(Map::new());
+                    }
+                    value
+                        .as_object_mut()
+                        .expect("object")
+                        .entry(key)
+                        .or_insert(Value::Null)
+                }
+                Segment::Index(index) => value
+                    .as_array_mut()
+                    .and_then(|items| items.get_mut(index))
+                    .ok_or_else(|| {
+                        ConfigError::InvalidArgument(format!("no sequence item at `{path}`"))
+                    })?,
+            };
+        }
+        *value = new;
+        Ok(())
+    }
+}
+
+/// Opens the configuration `name` in the configs directory.
+fn open(name: &str, format: Option<&str>, must_exist: bool) -> Result<MtcConfig, ConfigError> {
+    check_file_stem("configuration name", name)?;
+    // A table of a shared file has no file of its own
+    if let Some(config) = find_registered_config(name)
+        && let Some(section) = config.section()
+    {
+        let file = config.file_name();
+        return Err(ConfigError::InvalidArgument(format!(
+            "`{name}` is stored in the `{section}` table of `{file}`, open `{file}` instead"
+        )));
+    }
+    let dir = get_configs_dir();
+    let (path, format) = match format {
+        Some(format) => {
+            let format: ConfigFormat = format.parse()?;
+            (dir.join(format!("{name}.{}", format.extension())), format)
+        }
+        None => find_file(&dir, name).unwrap_or_else(|| {
+            let format = ConfigFormat::default();
+            (dir.join(format!("{name}.{}", format.extension())), format)
+        }),
+    };
+    let mut encrypted = false;
+    let sealed = integrity::is_enabled() && integrity::sidecar_path(&path).exists();
+    let value = if path.exists() {
+        if sealed {
+            integrity::verify(&path, integrity::load_key()?.as_deref())?;
+        }
+        let content = read_file(&path)?;
+        encrypted = encryption::is_sealed(&content);
+        format.deserialize(&read_plain(&path, name)?)?
+    } else if must_exist {
+        return Err(ConfigError::NotFound(path));
+    } else {
+        Value::Object(Map::new())
+    };
+    Ok(MtcConfig {
+        name: name.to_string(),
+        path,
+        format,
+        value,
+        encrypted,
+        sealed,
+    })
+}
+
+/// Finds the file of the configuration `name` like
+/// [`Configuration::find_existing`](crate::Configuration::find_existing): in
+/// the declared format of the registered type of that name first, then in
+/// the fallback formats, including those of registered codecs.
+fn find_file(dir: &Path, name: &str) -> Option<(PathBuf, ConfigFormat)> {
+    let declared = find_registered_config(name).map(|config| config.format());
+    declared
+        .map(|format| (format.extension(), format))
+        .into_iter()
+        .chain(fallback_formats())
+        .map(|(extension, format)| (dir.join(format!("{name}.{extension}")), format))
+        .find(|(path, _)| path.exists())
+}
+
+/// Reads the file at `path`, decrypting it if it is encrypted.
+fn read_plain(path: &Path, name: &str) -> Result<String, ConfigError> {
+    let content = read_file(path)?;
+    if encryption::is_sealed(&content) {
+        encryption::open(&content, &encryption::load_key()?, name)
+    } else {
+        Ok(content)
+    }
+}
+
+/// Opens a configuration from C arguments, returning a handle or null.
+///
+/// # Safety
+/// Same as [`mtc_config_load`].
+unsafe fn open_handle(
+    name: *const c_char,
+    format: *const c_char,
+    must_exist: bool,
+) -> *mut MtcConfig {
+    let opened = (|| {
+        // SAFETY: forwarded from the caller
+        let name = unsafe { arg(name, "name") }?;
+        let format = if format.is_null() {
+            None
+        } else {
+            // SAFETY: forwarded from the caller
+            Some(unsafe { arg(format, "format") }?)
+        };
+        open(name, format, must_exist)
+    })();
+    match opened {
+        Ok(config) => Box::into_raw(Box::new(config)),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Loads the configuration file `<name>.<ext>` from the configs directory.
+///
+/// `format` is a format name like `"toml"`; if null, the file is found like
+/// `load()` finds it: in the declared format of the registered type named
+/// `name`, then with any fallback extension such as `.yml` or the one of a
+/// registered codec. Returns null on failure.
+///
+/// Configurations declared with `section` are tables of a file shared with
+/// other types and can't be opened by their own name: open the shared file,
+/// e.g. `workspace`, and use paths in their table, e.g. `mkbook.title`.
+///
+/// # Safety
+/// `name` must point to a valid NUL-terminated string and `format` must be
+/// null or point to one. The returned handle must be released with
+/// [`mtc_config_free`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_load(
+    name: *const c_char,
+    format: *const c_char,
+) -> *mut MtcConfig {
+    // SAFETY: forwarded from the caller
+    unsafe { open_handle(name, format, true) }
+}
+
+/// Like [`mtc_config_load`], but starts from an empty configuration if the
+/// file does not exist yet.
+///
+/// # Safety
+/// Same as [`mtc_config_load`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_open(
+    name: *const c_char,
+    format: *const c_char,
+) -> *mut MtcConfig {
+    // SAFETY: forwarded from the caller
+    unsafe { open_handle(name, format, false) }
+}
+
+/// Releases a handle; null is ignored.
+///
+/// # Safety
+/// `config` must be null or a handle returned by this library that was not
+/// released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_free(config: *mut MtcConfig) {
+    if !config.is_null() {
+        // SAFETY: the caller guarantees the handle came from `Box::into_raw`
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+/// Reads a handle argument.
+///
+/// # Safety
+/// `config` must be null or a live handle.
+unsafe fn handle<'a>(config: *const MtcConfig) -> Result<&'a MtcConfig, ConfigError> {
+    // SAFETY: the caller guarantees the handle is live
+    unsafe { config.as_ref() }
+        .ok_or_else(|| ConfigError::InvalidArgument("`config` is null".to_string()))
+}
+
+/// Reads a mutable handle argument.
+///
+/// # Safety
+/// `config` must be null or a live handle not used elsewhere for the call.
+unsafe fn handle_mut<'a>(config: *mut MtcConfig) -> Result<&'a mut MtcConfig, ConfigError> {
+    // SAFETY: the caller guarantees the handle is live and not aliased
+    unsafe { config.as_mut() }
+        .ok_or_else(|| ConfigError::InvalidArgument("`config` is null".to_string()))
+}
+
+/// Writes `value` to `out`, failing on a null pointer.
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn write_out<T>(out: *mut T, value: T) -> Result<(), ConfigError> {
+    if out.is_null() {
+        return Err(ConfigError::InvalidArgument("`out` is null".to_string()));
+    }
+    // SAFETY: the caller guarantees `out` is valid for writes
+    unsafe { out.write(value) };
+    Ok(())
+}
+
+/// Gets the string at `path`. On success `*out` receives a new string that
+/// must be released with [`mtc_config_string_free`].
+///
+/// # Safety
+/// `config` must be a live handle, `path` a valid NUL-terminated string and
+/// `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_get_string(
+    config: *const MtcConfig,
+    path: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path) = unsafe { (handle(config)?, arg(path, "path")?) };
+        let value = config.get(path)?;
+        let string = value.as_str().ok_or_else(|| {
+            ConfigError::Deserialization(format!("`{path}` is not a string but {value}"))
+        })?;
+        let string =
+            CString::new(string).map_err(|e| ConfigError::Deserialization(e.to_string()))?;
+        // SAFETY: forwarded from the caller
+        unsafe { write_out(out, string.into_raw()) }
+    })
+}
+
+/// Gets the integer at `path` into `*out`.
+///
+/// # Safety
+/// `config` must be a live handle, `path` a valid NUL-terminated string and
+/// `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_get_int(
+    config: *const MtcConfig,
+    path: *const c_char,
+    out: *mut i64,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path) = unsafe { (handle(config)?, arg(path, "path")?) };
+        let value = config.get(path)?;
+        let int = value.as_i64().ok_or_else(|| {
+            ConfigError::Deserialization(format!("`{path}` is not an integer but {value}"))
+        })?;
+        // SAFETY: forwarded from the caller
+        unsafe { write_out(out, int) }
+    })
+}
+
+/// Gets the boolean at `path` into `*out`.
+///
+/// # Safety
+/// `config` must be a live handle, `path` a valid NUL-terminated string and
+/// `out` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_get_bool(
+    config: *const MtcConfig,
+    path: *const c_char,
+    out: *mut bool,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path) = unsafe { (handle(config)?, arg(path, "path")?) };
+        let value = config.get(path)?;
+        let boolean = value.as_bool().ok_or_else(|| {
+            ConfigError::Deserialization(format!("`{path}` is not a boolean but {value}"))
+        })?;
+        // SAFETY: forwarded from the caller
+        unsafe { write_out(out, boolean) }
+    })
+}
+
+/// Sets the string at `path`, creating missing tables on the way.
+///
+/// # Safety
+/// `config` must be a live handle, `path` and `value` valid NUL-terminated
+/// strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_set_string(
+    config: *mut MtcConfig,
+    path: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path, value) = unsafe {
+            (
+                handle_mut(config)?,
+                arg(path, "path")?,
+                arg(value, "value")?,
+            )
+        };
+        config.set(path, Value::String(value.to_string()))
+    })
+}
+
+/// Sets the integer at `path`, creating missing tables on the way.
+///
+/// # Safety
+/// `config` must be a live handle and `path` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_set_int(
+    config: *mut MtcConfig,
+    path: *const c_char,
+    value: i64,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path) = unsafe { (handle_mut(config)?, arg(path, "path")?) };
+        config.set(path, Value::from(value))
+    })
+}
+
+/// Sets the boolean at `path`, creating missing tables on the way.
+///
+/// # Safety
+/// `config` must be a live handle and `path` a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_set_bool(
+    config: *mut MtcConfig,
+    path: *const c_char,
+    value: bool,
+) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let (config, path) = unsafe { (handle_mut(config)?, arg(path, "path")?) };
+        config.set(path, Value::Bool(value))
+    })
+}
+
+/// Writes the configuration back to its file, creating the configs directory
+/// if needed. The file is replaced atomically and, for a registered type
+/// keeping backups, the previous version is backed up like `save()` does.
+///
+/// # Safety
+/// `config` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_save(config: *const MtcConfig) -> c_int {
+    status(|| {
+        // SAFETY: forwarded from the caller
+        let config = unsafe { handle(config) }?;
+        let content = config.format.serialize(&config.value)?;
+        if let Some(parent) = config.path.parent() {
+            fs::create_dir_all(parent).map_err(ConfigError::Io)?;
+        }
+        // Back up the previous file like `save()` of the registered type would
+        if let Some(registered) = find_registered_config(&config.name)
+            && registered.history_limit() > 0
+            && registered.default_path() == config.path
+            && config.path.exists()
+            && read_plain(&config.path, &config.name)? != content
+        {
+            history::archive(
+                &config.path,
+                config.format,
+                &registered.history_dir(),
+                registered.history_limit(),
+            )?;
+        }
+        let sealed = if config.encrypted {
+            encryption::seal(&content, &encryption::load_key()?, &config.name)?
+        } else {
+            content
+        };
+        let integrity_key = if config.sealed {
+            Some(integrity::load_key()?)
+        } else {
+            None
+        };
+        write_sealed_file(
+            &config.path,
+            &sealed,
+            integrity_key.as_ref().map(Option::as_deref),
+        )
+    })
+}
+
+/// Gets the message of the last failure on the calling thread, or null if
+/// nothing failed yet. The string stays valid until the next failing call on
+/// the same thread and must not be released.
+#[unsafe(no_mangle)]
+pub extern "C" fn mtc_config_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Releases a string returned by [`mtc_config_get_string`]; null is ignored.
+///
+/// # Safety
+/// `string` must be null or a string returned by this library that was not
+/// released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mtc_config_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the caller guarantees the string came from `CString::into_raw`
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
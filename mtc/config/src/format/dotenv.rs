@@ -0,0 +1,75 @@
+//! Dotenv codec.
+//!
+//! Only flat configurations are supported: every field becomes one
+//! `UPPERCASE_NAME=value` line and keys are lowercased again when loading.
+//! [`write_line`] also writes the variables of
+//! [`environment::export`](crate::environment::export).
+
+#[cfg(feature = "dotenv")]
+use serde::Serialize;
+#[cfg(feature = "dotenv")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "dotenv")]
+use serde_json::Map;
+use serde_json::Value;
+
+use super::scalar;
+#[cfg(feature = "dotenv")]
+use crate::ConfigError;
+
+/// Writes one `NAME=value` line, or nothing for `null`.
+///
+/// # Errors
+/// A short description of the value if it is a sequence or a table.
+pub(crate) fn write_line(out: &mut String, name: &str, value: &Value) -> Result<(), &'static str> {
+    if let Some(encoded) = scalar::encode(value)? {
+        out.push_str(&format!("{name}={encoded}\n"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "dotenv")]
+pub(crate) fn to_string<T: Serialize>(value: &T) -> Result<String, ConfigError> {
+    let root = match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => {
+            return Err(ConfigError::Serialization(
+                "dotenv requires the configuration to serialize as a table".into(),
+            ));
+        }
+        Err(e) => return Err(ConfigError::Serialization(e.to_string())),
+    };
+
+    let mut out = String::new();
+    for (key, value) in &root {
+        write_line(&mut out, &key.to_uppercase(), value).map_err(|kind| {
+            ConfigError::Serialization(format!(
+                "dotenv output requires a flat configuration, `{key}` is {kind}"
+            ))
+        })?;
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "dotenv")]
+pub(crate) fn from_str<T: DeserializeOwned>(content: &str) -> Result<T, ConfigError> {
+    let mut root = Map::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, raw)) = line.split_once('=') else {
+            return Err(ConfigError::Deserialization(format!(
+                "line {}: expected `KEY=value`",
+                number + 1
+            )));
+        };
+        root.insert(key.trim().to_lowercase(), scalar::decode(raw));
+    }
+
+    serde_json::from_value(Value::Object(root))
+        .map_err(|e| ConfigError::Deserialization(e.to_string()))
+}
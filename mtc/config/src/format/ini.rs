@@ -0,0 +1,96 @@
+//! INI codec.
+//!
+//! The configuration is mapped through a `serde_json::Value`: top-level scalars
+//! go before the first section header and every nested struct becomes a
+//! `[section]` of scalars.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use super::scalar;
+use crate::ConfigError;
+
+pub(crate) fn to_string<T: Serialize>(value: &T) -> Result<String, ConfigError> {
+    let root = match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) => {
+            return Err(ConfigError::Serialization(
+                "INI requires the configuration to serialize as a table".into(),
+            ));
+        }
+        Err(e) => return Err(ConfigError::Serialization(e.to_string())),
+    };
+
+    let mut out = String::new();
+    let mut sections = Vec::new();
+    for (key, value) in &root {
+        match value {
+            Value::Object(section) => sections.push((key, section)),
+            value => write_entry(&mut out, key, value, key)?,
+        }
+    }
+    for (name, section) in sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{name}]\n"));
+        for (key, value) in section {
+            write_entry(&mut out, key, value, &format!("{name}.{key}"))?;
+        }
+    }
+    Ok(out)
+}
+
+fn write_entry(out: &mut String, key: &str, value: &Value, path: &str) -> Result<(), ConfigError> {
+    match scalar::encode(value) {
+        Ok(Some(encoded)) => {
+            out.push_str(&format!("{key} = {encoded}\n"));
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(kind) => Err(ConfigError::Serialization(format!(
+            "INI can't represent `{path}`: it is {kind}"
+        ))),
+    }
+}
+
+pub(crate) fn from_str<T: DeserializeOwned>(content: &str) -> Result<T, ConfigError> {
+    let mut root = Map::new();
+    let mut section: Option<String> = None;
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim().to_string();
+            root.entry(name.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            section = Some(name);
+            continue;
+        }
+        let Some((key, raw)) = line.split_once('=') else {
+            return Err(ConfigError::Deserialization(format!(
+                "line {}: expected `key = value` or `[section]`",
+                number + 1
+            )));
+        };
+        let key = key.trim().to_string();
+        let value = scalar::decode(raw);
+        match &section {
+            None => {
+                root.insert(key, value);
+            }
+            Some(name) => {
+                if let Some(Value::Object(table)) = root.get_mut(name) {
+                    table.insert(key, value);
+                }
+            }
+        }
+    }
+
+    serde_json::from_value(Value::Object(root))
+        .map_err(|e| ConfigError::Deserialization(e.to_string()))
+}
@@ -0,0 +1,298 @@
+//! Serialization formats supported by [`Configuration`](crate::Configuration).
+//!
+//! Every format except TOML is optional and has to be enabled with the cargo
+//! feature of the same name. `#[config(format = "...")]` rejects disabled
+//! formats at compile time; a disabled format selected any other way is read
+//! and written by the codec registered for it in the [`FormatRegistry`], and
+//! fails at runtime when there is none.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ConfigError;
+
+pub use registry::{ConfigCodec, FormatRegistry};
+
+// Always built, `environment::export` writes `.env` files with it
+pub(crate) mod dotenv;
+#[cfg(feature = "ini")]
+mod ini;
+mod registry;
+mod scalar;
+
+/// Supported formats for configuration serialization and deserialization.
+///
+/// This enum defines the available formats for storing configuration data.
+/// The actual support for each format depends on the enabled feature flags:
+/// - `json`: Enables JSON format support
+/// - `toml`: Enables TOML format support (default)
+/// - `yaml`: Enables YAML format support
+/// - `ron`: Enables RON format support
+/// - `json5`: Enables JSON5 format support
+/// - `ini`: Enables INI format support (one level of sections)
+/// - `dotenv`: Enables `.env` format support (flat configurations only)
+///
+/// Other formats are provided by codecs registered at runtime, see
+/// [`FormatRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    /// JSON format (requires the "json" feature)
+    Json,
+    /// TOML format (requires the "toml" feature)
+    Toml,
+    /// YAML format (requires the "yaml" feature)
+    Yaml,
+    /// RON format (requires the "ron" feature)
+    Ron,
+    /// JSON5 format (requires the "json5" feature)
+    Json5,
+    /// INI format (requires the "ini" feature)
+    ///
+    /// Top-level scalar fields are written before the first section and every
+    /// nested struct becomes a `[section]`. Deeper nesting and sequences are
+    /// rejected.
+    Ini,
+    /// Dotenv format (requires the "dotenv" feature)
+    ///
+    /// Every field is written as an `UPPERCASE_NAME=value` line, so only flat
+    /// configurations with scalar fields are supported. Keys are lowercased
+    /// again on load.
+    Dotenv,
+    /// A format provided by the codec registered under this name in the
+    /// [`FormatRegistry`]
+    Custom(&'static str),
+}
+
+impl ConfigFormat {
+    /// All built-in formats, whether or not their feature is enabled.
+    pub const ALL: [ConfigFormat; 7] = [
+        ConfigFormat::Json,
+        ConfigFormat::Toml,
+        ConfigFormat::Yaml,
+        ConfigFormat::Ron,
+        ConfigFormat::Json5,
+        ConfigFormat::Ini,
+        ConfigFormat::Dotenv,
+    ];
+
+    /// Gets the name of the format, as accepted by `#[config(format = "...")]`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Ron => "ron",
+            ConfigFormat::Json5 => "json5",
+            ConfigFormat::Ini => "ini",
+            ConfigFormat::Dotenv => "dotenv",
+            ConfigFormat::Custom(name) => name,
+        }
+    }
+
+    /// Gets the file extension used for configuration files in this format.
+    ///
+    /// Custom formats use the extension of their codec, or their name if
+    /// the codec is not registered.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Dotenv => "env",
+            ConfigFormat::Custom(name) => FormatRegistry::get(name)
+                .map(|codec| codec.extension())
+                .unwrap_or(name),
+            other => other.name(),
+        }
+    }
+
+    /// Parses the name or an alias of a built-in format.
+    fn builtin(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "ron" => Some(ConfigFormat::Ron),
+            "json5" => Some(ConfigFormat::Json5),
+            "ini" => Some(ConfigFormat::Ini),
+            "dotenv" | "env" => Some(ConfigFormat::Dotenv),
+            _ => None,
+        }
+    }
+
+    /// Gets the format a registered codec provides.
+    fn of_codec(codec: &dyn ConfigCodec) -> Self {
+        ConfigFormat::builtin(codec.name()).unwrap_or(ConfigFormat::Custom(codec.name()))
+    }
+
+    /// Checks whether the cargo feature for this format is enabled.
+    ///
+    /// This is a `const fn` so the derive macro can reject disabled formats
+    /// at compile time. Custom formats have no feature and always return
+    /// false, see [`ConfigFormat::is_available`].
+    pub const fn is_enabled(&self) -> bool {
+        match self {
+            ConfigFormat::Json => cfg!(feature = "json"),
+            ConfigFormat::Toml => cfg!(feature = "toml"),
+            ConfigFormat::Yaml => cfg!(feature = "yaml"),
+            ConfigFormat::Ron => cfg!(feature = "ron"),
+            ConfigFormat::Json5 => cfg!(feature = "json5"),
+            ConfigFormat::Ini => cfg!(feature = "ini"),
+            ConfigFormat::Dotenv => cfg!(feature = "dotenv"),
+            ConfigFormat::Custom(_) => false,
+        }
+    }
+
+    /// Checks whether this format can be used, because its feature is enabled
+    /// or a codec is registered for it.
+    pub fn is_available(&self) -> bool {
+        self.is_enabled() || FormatRegistry::get(self.name()).is_some()
+    }
+
+    /// Gets the registered codec of a format that is not compiled in.
+    ///
+    /// # Errors
+    /// Why the format can't be used.
+    fn codec(&self) -> Result<std::sync::Arc<dyn ConfigCodec>, String> {
+        FormatRegistry::get(self.name()).ok_or_else(|| match self {
+            ConfigFormat::Custom(name) => format!(
+                "format `{name}` is not registered, see `FormatRegistry::register`"
+            ),
+            builtin => format!(
+                "format `{builtin}` is not enabled, enable the `{builtin}` feature of mtc-config or register a codec for it"
+            ),
+        })
+    }
+
+    /// Serializes a value into a string in this format.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the value can't be represented in this
+    ///   format or the format is not enabled
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String, ConfigError> {
+        match self {
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => serde_json::to_string_pretty(value)
+                .map_err(|e| ConfigError::Serialization(e.to_string())),
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => {
+                toml::to_string(value).map_err(|e| ConfigError::Serialization(e.to_string()))
+            }
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => {
+                serde_yaml::to_string(value).map_err(|e| ConfigError::Serialization(e.to_string()))
+            }
+            #[cfg(feature = "ron")]
+            ConfigFormat::Ron => {
+                ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                    .map_err(|e| ConfigError::Serialization(e.to_string()))
+            }
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5 => {
+                json5::to_string(value).map_err(|e| ConfigError::Serialization(e.to_string()))
+            }
+            #[cfg(feature = "ini")]
+            ConfigFormat::Ini => ini::to_string(value),
+            #[cfg(feature = "dotenv")]
+            ConfigFormat::Dotenv => dotenv::to_string(value),
+            _ => {
+                let codec = self.codec().map_err(ConfigError::Serialization)?;
+                let value = serde_json::to_value(value)
+                    .map_err(|e| ConfigError::Serialization(e.to_string()))?;
+                codec.serialize(&value)
+            }
+        }
+    }
+
+    /// Deserializes a value from a string in this format.
+    ///
+    /// # Errors
+    /// - `ConfigError::Deserialization`: If the content is not valid for this
+    ///   format or the format is not enabled
+    pub fn deserialize<T: DeserializeOwned>(&self, content: &str) -> Result<T, ConfigError> {
+        match self {
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => serde_json::from_str(content)
+                .map_err(|e| ConfigError::Deserialization(e.to_string())),
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => {
+                toml::from_str(content).map_err(|e| ConfigError::Deserialization(e.to_string()))
+            }
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::from_str(content)
+                .map_err(|e| ConfigError::Deserialization(e.to_string())),
+            #[cfg(feature = "ron")]
+            ConfigFormat::Ron => {
+                ron::from_str(content).map_err(|e| ConfigError::Deserialization(e.to_string()))
+            }
+            #[cfg(feature = "json5")]
+            ConfigFormat::Json5 => {
+                json5::from_str(content).map_err(|e| ConfigError::Deserialization(e.to_string()))
+            }
+            #[cfg(feature = "ini")]
+            ConfigFormat::Ini => ini::from_str(content),
+            #[cfg(feature = "dotenv")]
+            ConfigFormat::Dotenv => dotenv::from_str(content),
+            _ => {
+                let codec = self.codec().map_err(ConfigError::Deserialization)?;
+                serde_json::from_value(codec.deserialize(content)?)
+                    .map_err(|e| ConfigError::Deserialization(e.to_string()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    /// Writes the name of the format: "json", "toml", "yaml", "ron", "json5", "ini",
+    /// "dotenv" or the name of a custom format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = ConfigError;
+
+    /// Parses a format name.
+    ///
+    /// Besides the names returned by [`ConfigFormat::name`], the file extensions
+    /// `yml` and `env` are accepted as aliases. Other names and extensions
+    /// refer to the codecs registered in the [`FormatRegistry`].
+    ///
+    /// # Errors
+    /// - `ConfigError::UnknownFormat`: If the string does not name a known format
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ConfigFormat::builtin(value)
+            .or_else(|| FormatRegistry::get(value).map(|codec| ConfigFormat::of_codec(&*codec)))
+            .ok_or_else(|| ConfigError::UnknownFormat(value.to_string()))
+    }
+}
+
+impl TryFrom<&str> for ConfigFormat {
+    type Error = ConfigError;
+
+    /// Creates a ConfigFormat from a string slice, see [`ConfigFormat::from_str`].
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for ConfigFormat {
+    type Error = ConfigError;
+
+    /// Creates a ConfigFormat from a string, see [`ConfigFormat::from_str`].
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl Default for ConfigFormat {
+    /// Returns the default configuration format (TOML).
+    ///
+    /// # Returns
+    /// ConfigFormat::Toml as the default format.
+    fn default() -> Self {
+        // Default to TOML as the standard configuration format
+        ConfigFormat::Toml
+    }
+}
@@ -0,0 +1,177 @@
+//! Codecs selectable at runtime by format name.
+//!
+//! The built-in formats whose cargo feature is enabled are registered from
+//! the start. Applications add their own with [`FormatRegistry::register`],
+//! either for a new format, e.g. an internal one, or for a built-in format
+//! whose feature is disabled, without rebuilding mtc-config:
+//!
+//! ```ignore
+//! FormatRegistry::register(InternalCodec)?;
+//! let format: ConfigFormat = "internal".parse()?;
+//! AppConfig::convert_to(format)?;
+//! ```
+//!
+//! Codecs exchange values as [`serde_json::Value`], so every type that
+//! serializes to JSON can be stored with them.
+
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use serde_json::Value;
+
+use super::ConfigFormat;
+use crate::ConfigError;
+
+/// Reads and writes configuration files in one format.
+pub trait ConfigCodec: Send + Sync {
+    /// Gets the name of the format, e.g. for `#[config(codec = "...")]`.
+    fn name(&self) -> &'static str;
+
+    /// Gets the extension of files in this format, the name by default.
+    fn extension(&self) -> &'static str {
+        self.name()
+    }
+
+    /// Writes a value in this format.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the value can't be represented in this format
+    fn serialize(&self, value: &Value) -> Result<String, ConfigError>;
+
+    /// Reads a value in this format.
+    ///
+    /// # Errors
+    /// - `ConfigError::Deserialization`: If the content is not valid for this format
+    fn deserialize(&self, content: &str) -> Result<Value, ConfigError>;
+}
+
+/// A built-in format, registered when its feature is enabled.
+struct BuiltinCodec(ConfigFormat);
+
+impl ConfigCodec for BuiltinCodec {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn extension(&self) -> &'static str {
+        self.0.extension()
+    }
+
+    fn serialize(&self, value: &Value) -> Result<String, ConfigError> {
+        self.0.serialize(value)
+    }
+
+    fn deserialize(&self, content: &str) -> Result<Value, ConfigError> {
+        self.0.deserialize(content)
+    }
+}
+
+/// The codecs known to this process.
+pub struct FormatRegistry;
+
+impl FormatRegistry {
+    /// Gets the registered codecs, starting with the enabled built-in formats.
+    fn codecs() -> &'static RwLock<Vec<Arc<dyn ConfigCodec>>> {
+        static CODECS: OnceLock<RwLock<Vec<Arc<dyn ConfigCodec>>>> = OnceLock::new();
+        CODECS.get_or_init(|| {
+            let builtins = ConfigFormat::ALL
+                .into_iter()
+                .filter(ConfigFormat::is_enabled)
+                .map(|format| Arc::new(BuiltinCodec(format)) as Arc<dyn ConfigCodec>);
+            RwLock::new(builtins.collect())
+        })
+    }
+
+    /// Registers a codec.
+    ///
+    /// # Returns
+    /// The format referring to the codec: the built-in variant if the codec
+    /// provides a built-in format whose feature is disabled, keeping the
+    /// built-in extension, or [`ConfigFormat::Custom`] otherwise.
+    ///
+    /// # Errors
+    /// - `ConfigError::InvalidArgument`: If the name or extension is invalid,
+    ///   belongs to an enabled built-in format or to a registered codec
+    pub fn register(codec: impl ConfigCodec + 'static) -> Result<ConfigFormat, ConfigError> {
+        let (name, extension) = (codec.name(), codec.extension());
+        for key in [name, extension] {
+            let valid = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if !valid {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "invalid format name or extension `{key}`, only ASCII letters, digits, `_` and `-` are allowed"
+                )));
+            }
+        }
+        let format = match ConfigFormat::builtin(name) {
+            Some(format) if format.name() != name => {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "`{name}` is an alias of the built-in `{format}` format, register it as `{format}`"
+                )));
+            }
+            Some(format) if format.is_enabled() => {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "the built-in `{name}` format is enabled and can't be replaced"
+                )));
+            }
+            Some(format) => format,
+            None => match ConfigFormat::builtin(extension) {
+                Some(format) => {
+                    return Err(ConfigError::InvalidArgument(format!(
+                        "extension `.{extension}` belongs to the built-in `{format}` format"
+                    )));
+                }
+                None => ConfigFormat::Custom(name),
+            },
+        };
+        let extension = match format {
+            ConfigFormat::Custom(_) => extension,
+            builtin => builtin.extension(),
+        };
+
+        let mut codecs = Self::codecs()
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let taken = codecs.iter().any(|registered| {
+            [registered.name(), registered.extension()]
+                .iter()
+                .any(|key| *key == name || *key == extension)
+        });
+        if taken {
+            return Err(ConfigError::InvalidArgument(format!(
+                "a codec named `{name}` or using `.{extension}` is already registered"
+            )));
+        }
+        codecs.push(Arc::new(codec));
+        Ok(format)
+    }
+
+    /// Finds the codec of a format by name or extension.
+    ///
+    /// # Returns
+    /// The codec, or None if no registered codec has this name or extension.
+    pub fn get(name: &str) -> Option<Arc<dyn ConfigCodec>> {
+        let codecs = Self::codecs()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        codecs
+            .iter()
+            .find(|codec| codec.name() == name || codec.extension() == name)
+            .cloned()
+    }
+
+    /// Lists the formats that can be used, in the order they were registered.
+    ///
+    /// # Returns
+    /// The enabled built-in formats, then those provided by registered codecs.
+    pub fn formats() -> Vec<ConfigFormat> {
+        let codecs = Self::codecs()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        codecs
+            .iter()
+            .map(|codec| ConfigFormat::of_codec(&**codec))
+            .collect()
+    }
+}
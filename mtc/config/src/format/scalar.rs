@@ -0,0 +1,101 @@
+//! Scalar encoding shared by the line-based formats (INI and dotenv).
+//!
+//! Values are written bare whenever reading them back yields the same value,
+//! otherwise they are double-quoted with backslash escapes. Reading infers
+//! booleans and numbers from bare values, so the string `"42"` survives a
+//! round trip while the integer `42` does too.
+
+use serde_json::{Number, Value};
+
+/// Encodes a scalar value, returning `None` for `null` (the key is omitted)
+/// and `Err` with a short description for sequences and maps.
+pub(crate) fn encode(value: &Value) -> Result<Option<String>, &'static str> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(b) => Ok(Some(b.to_string())),
+        Value::Number(n) => Ok(Some(n.to_string())),
+        Value::String(s) => Ok(Some(encode_str(s))),
+        Value::Array(_) => Err("a sequence"),
+        Value::Object(_) => Err("a nested table"),
+    }
+}
+
+fn encode_str(s: &str) -> String {
+    let needs_quotes = s.is_empty()
+        || s.trim() != s
+        || s.contains(['"', '\'', '\\', '#', ';', '=', '\n', '\r', '\t'])
+        || decode(s) != Value::String(s.to_string());
+    if !needs_quotes {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Decodes a raw value as written by [`encode`] or by hand.
+///
+/// Double-quoted values are unescaped, single-quoted values are taken
+/// literally, `true`/`false` become booleans and numeric literals become
+/// numbers. Anything else is a string.
+pub(crate) fn decode(raw: &str) -> Value {
+    let raw = raw.trim();
+    if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+        return Value::String(unescape(&raw[1..raw.len() - 1]));
+    }
+    if raw.len() >= 2 && raw.starts_with('\'') && raw.ends_with('\'') {
+        return Value::String(raw[1..raw.len() - 1].to_string());
+    }
+    match raw {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    let numeric = !raw.is_empty()
+        && raw.chars().any(|c| c.is_ascii_digit())
+        && raw
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'));
+    if numeric {
+        if let Ok(i) = raw.parse::<i64>() {
+            return Value::Number(i.into());
+        }
+        if let Ok(u) = raw.parse::<u64>() {
+            return Value::Number(u.into());
+        }
+        if let Some(f) = raw.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(f);
+        }
+    }
+    Value::String(raw.to_string())
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,101 @@
+//! Process-wide shared configuration instances.
+//!
+//! `#[config(global)]` makes the derive generate `global()`, `reload_global()`
+//! and, in test builds, `override_global()` on the type. They are thin
+//! wrappers around a `static` [`GlobalConfig`].
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+use crate::{ConfigError, Configuration};
+
+/// A lazily loaded configuration shared by the whole process.
+pub struct GlobalConfig<T> {
+    value: RwLock<Option<Arc<T>>>,
+    overrides: Mutex<()>,
+}
+
+impl<T> Default for GlobalConfig<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> GlobalConfig<T> {
+    /// Creates an empty instance; nothing is loaded until first use.
+    pub const fn new() -> Self {
+        Self {
+            value: RwLock::new(None),
+            overrides: Mutex::new(()),
+        }
+    }
+}
+
+impl<T: Configuration> GlobalConfig<T> {
+    /// Gets the shared configuration, loading it with
+    /// [`Configuration::load_or_default`] on first use.
+    pub fn get(&self) -> Arc<T> {
+        if let Some(config) = self
+            .value
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+        {
+            return Arc::clone(config);
+        }
+        let mut value = self.value.write().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(value.get_or_insert_with(|| Arc::new(T::load_or_default())))
+    }
+
+    /// Reloads the shared configuration from disk.
+    ///
+    /// Values handed out earlier are not affected, callers have to call
+    /// [`GlobalConfig::get`] again to see the reloaded configuration.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::load`]; the previous value is kept on error.
+    pub fn reload(&self) -> Result<Arc<T>, ConfigError> {
+        let config = Arc::new(T::load()?);
+        *self.value.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::clone(&config));
+        Ok(config)
+    }
+
+    /// Replaces the shared configuration until the returned guard is dropped.
+    ///
+    /// Meant for tests: nothing is read from or written to disk. Overrides of
+    /// the same configuration are serialized, a second call blocks until the
+    /// first guard is dropped.
+    pub fn override_with(&self, config: T) -> GlobalOverride<'_, T> {
+        let lock = self
+            .overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let previous = self
+            .value
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(Arc::new(config));
+        GlobalOverride {
+            global: self,
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+/// Guard returned by [`GlobalConfig::override_with`], restores the previous
+/// shared configuration when dropped.
+pub struct GlobalOverride<'a, T> {
+    global: &'a GlobalConfig<T>,
+    previous: Option<Arc<T>>,
+    _lock: MutexGuard<'a, ()>,
+}
+
+impl<T> Drop for GlobalOverride<'_, T> {
+    fn drop(&mut self) {
+        *self
+            .global
+            .value
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = self.previous.take();
+    }
+}
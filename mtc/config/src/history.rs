@@ -0,0 +1,138 @@
+//! Rolling backups of saved configuration files.
+//!
+//! When a [`Configuration`](crate::Configuration) keeps history, every `save()`
+//! that changes the file first copies the previous version into
+//! `<configs dir>/.history/<config name>/<timestamp>.<ext>` and prunes the
+//! oldest copies beyond the configured limit.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::{ConfigError, ConfigFormat};
+
+/// Format of the timestamp used as version id; sorts chronologically.
+const ID_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+
+/// Version id that refers to the live configuration file rather than a backup.
+pub const CURRENT_VERSION: &str = "current";
+
+/// A backed up version of a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigVersion {
+    /// The version id, a UTC timestamp of when the backup was taken.
+    pub id: String,
+    /// The path of the backup file.
+    pub path: PathBuf,
+    /// The format the backup is stored in.
+    pub format: ConfigFormat,
+}
+
+impl ConfigVersion {
+    /// Gets the time the backup was taken, parsed from the version id.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        let stamp = self.id.split('-').next()?;
+        NaiveDateTime::parse_from_str(stamp, ID_FORMAT)
+            .ok()
+            .map(|naive| naive.and_utc())
+    }
+}
+
+/// Copies `path` into `dir` under a fresh timestamp id, then keeps only the
+/// newest `limit` backups.
+pub(crate) fn archive(
+    path: &Path,
+    format: ConfigFormat,
+    dir: &Path,
+    limit: usize,
+) -> Result<(), ConfigError> {
+    fs::create_dir_all(dir).map_err(ConfigError::Io)?;
+
+    let stamp = Utc::now().format(ID_FORMAT).to_string();
+    let mut id = stamp.clone();
+    let mut counter = 1;
+    while dir.join(format!("{id}.{}", format.extension())).exists() {
+        id = format!("{stamp}-{counter}");
+        counter += 1;
+    }
+    fs::copy(path, dir.join(format!("{id}.{}", format.extension()))).map_err(ConfigError::Io)?;
+
+    for stale in list(dir)?.into_iter().skip(limit) {
+        fs::remove_file(stale.path).map_err(ConfigError::Io)?;
+    }
+    Ok(())
+}
+
+/// Lists the backups in `dir`, newest first.
+pub(crate) fn list(dir: &Path) -> Result<Vec<ConfigVersion>, ConfigError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(dir).map_err(ConfigError::Io)? {
+        let path = entry.map_err(ConfigError::Io)?.path();
+        let (Some(id), Some(extension)) = (path.file_stem(), path.extension()) else {
+            continue;
+        };
+        let Ok(format) = extension.to_string_lossy().parse() else {
+            continue;
+        };
+        versions.push(ConfigVersion {
+            id: id.to_string_lossy().to_string(),
+            path,
+            format,
+        });
+    }
+    // Ids sharing a timestamp get a `-N` suffix, so compare numerically on it
+    versions.sort_by_key(|version| {
+        let (stamp, counter) = version.id.split_once('-').unwrap_or((&version.id, "0"));
+        (stamp.to_string(), counter.parse::<u64>().unwrap_or(0))
+    });
+    versions.reverse();
+    Ok(versions)
+}
+
+/// Finds the backup with the given id in `dir`.
+pub(crate) fn find(dir: &Path, id: &str) -> Result<ConfigVersion, ConfigError> {
+    list(dir)?
+        .into_iter()
+        .find(|version| version.id == id)
+        .ok_or_else(|| ConfigError::VersionNotFound(id.to_string()))
+}
+
+/// Renders a line diff between two texts, prefixing removed lines with `-`,
+/// added lines with `+` and unchanged lines with a space.
+pub(crate) fn line_diff(from: &str, to: &str) -> String {
+    let a: Vec<&str> = from.lines().collect();
+    let b: Vec<&str> = to.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push_str(&format!(" {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("-{}\n", a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", b[j]));
+            j += 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,128 @@
+//! Checksums detecting configuration files edited outside of `save()`.
+//!
+//! Configurations declared with `#[config(integrity)]` get a sidecar file
+//! next to them, `<file>.sum`, rewritten by every `save()` and checked by
+//! every `load()`. The sidecar holds `sha256:<hex>` of the file as written,
+//! or `hmac-sha256:<hex>` when a key is set in [`KEY_ENV`] or in the file
+//! named by [`KEY_FILE_ENV`]. A plain checksum only catches accidental
+//! edits; with a key, someone able to edit the file can't forge the sidecar.
+//!
+//! After a deliberate manual edit, [`Configuration::reseal`](crate::Configuration::reseal)
+//! accepts the file as it is. Requires the `integrity` feature.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ConfigError;
+
+/// Environment variable holding the HMAC key.
+pub const KEY_ENV: &str = "MTC_CONFIG_INTEGRITY_KEY";
+
+/// Environment variable naming a file that holds the HMAC key.
+pub const KEY_FILE_ENV: &str = "MTC_CONFIG_INTEGRITY_KEY_FILE";
+
+/// Checks whether the `integrity` feature is enabled.
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "integrity")
+}
+
+/// Gets the path of the sidecar checksum file of a configuration file.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sum");
+    PathBuf::from(name)
+}
+
+/// Reads the HMAC key from [`KEY_ENV`] or the file named by [`KEY_FILE_ENV`].
+///
+/// Surrounding whitespace is trimmed from both, so `KEY=$(cat keyfile)` and
+/// the key file give the same checksums.
+///
+/// # Returns
+/// The key bytes, or None if neither is set and plain checksums are used.
+///
+/// # Errors
+/// - `ConfigError::Io`: If the key file can't be read
+pub fn load_key() -> Result<Option<Vec<u8>>, ConfigError> {
+    match (crate::env_var(KEY_ENV), crate::env_var(KEY_FILE_ENV)) {
+        (Some(key), _) => Ok(Some(key.trim().as_bytes().to_vec())),
+        (None, Some(path)) => {
+            let key = fs::read_to_string(path).map_err(ConfigError::Io)?;
+            Ok(Some(key.trim().as_bytes().to_vec()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Computes the sidecar line for `content`: `hmac-sha256:<hex>` with a key,
+/// `sha256:<hex>` without.
+#[cfg(feature = "integrity")]
+pub fn checksum(content: &[u8], key: Option<&[u8]>) -> String {
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    match key {
+        Some(key) => {
+            let mut mac =
+                Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(content);
+            format!(
+                "hmac-sha256:{}",
+                crate::hex::encode(&mac.finalize().into_bytes())
+            )
+        }
+        None => format!("sha256:{}", crate::hex::encode(&Sha256::digest(content))),
+    }
+}
+
+/// Writes the sidecar of the file at `path` from its current content.
+pub(crate) fn seal(path: &Path, key: Option<&[u8]>) -> Result<(), ConfigError> {
+    let content = fs::read(path).map_err(ConfigError::Io)?;
+    seal_content(path, &content, key)
+}
+
+/// Writes the sidecar of the file at `path` for `content`, before the file
+/// itself is written.
+pub(crate) fn seal_content(
+    path: &Path,
+    content: &[u8],
+    key: Option<&[u8]>,
+) -> Result<(), ConfigError> {
+    let line = format!("{}\n", digest(content, key)?);
+    crate::write_atomic(&sidecar_path(path), line.as_bytes())
+}
+
+/// Checks the file at `path` against its sidecar.
+///
+/// A missing sidecar, or one made with a plain checksum while a key is set
+/// (or the other way round), is a mismatch.
+pub(crate) fn verify(path: &Path, key: Option<&[u8]>) -> Result<(), ConfigError> {
+    let mismatch = || ConfigError::IntegrityMismatch(path.to_path_buf());
+    let expected = fs::read_to_string(sidecar_path(path)).map_err(|_| mismatch())?;
+    let content = fs::read(path).map_err(ConfigError::Io)?;
+    if constant_time_eq(
+        expected.trim().as_bytes(),
+        digest(&content, key)?.as_bytes(),
+    ) {
+        Ok(())
+    } else {
+        Err(mismatch())
+    }
+}
+
+/// Compares without returning early, so the timing doesn't leak how much matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(feature = "integrity")]
+fn digest(content: &[u8], key: Option<&[u8]>) -> Result<String, ConfigError> {
+    Ok(checksum(content, key))
+}
+
+#[cfg(not(feature = "integrity"))]
+fn digest(_content: &[u8], _key: Option<&[u8]>) -> Result<String, ConfigError> {
+    Err(ConfigError::Serialization(
+        "integrity checks require the `integrity` feature".to_string(),
+    ))
+}
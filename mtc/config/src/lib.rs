@@ -5,10 +5,32 @@
 //!
 //! # Features
 //!
-//! - Support for multiple serialization formats (TOML, JSON, YAML) via feature flags
+//! - Support for multiple serialization formats (TOML, JSON, YAML, RON, JSON5, INI, dotenv) via feature flags
 //! - Automatic configuration directory creation
 //! - Customizable configuration names and paths
 //! - Built-in error handling for configuration operations
+//! - Optional rolling backups with restore and diff between versions
+//! - Structural diff against another value, the saved file or the defaults
+//! - Registry of every derived configuration type in the binary
+//! - Linting of every file in the configs directory against its registered type with [`lint_dir`]
+//! - Field metadata (type, doc, default, validation rules, secrets) via [`Configuration::schema`]
+//! - Duration, byte size, expanded path, URL and socket address field types written as `30s`, `10MiB`, `~/data` in [`types`]
+//! - Several types as separate tables of one shared file with `#[config(section = "...", file = "...")]`
+//! - Several named instances of one type, e.g. per tenant, stored as `<name>/<instance>.toml` with `#[config(multi)]`
+//! - Hand-written default files embedded with `#[config(default_file = "...")]`
+//! - Encryption at rest with `#[config(encrypted)]` (`encryption` feature)
+//! - Checksum sidecars detecting outside edits with `#[config(integrity)]` (`integrity` feature)
+//! - Export as `PREFIX_SECTION__KEY` environment variables (`.env`, shell, docker-compose) and overrides from them with `#[config(env_prefix = "...")]`
+//! - Interactive terminal editor prompting for every field with its docs, default and rules
+//! - Custom formats from codecs registered at runtime in a `FormatRegistry`, selected by name or with `#[config(codec = "...")]`, also standing in for built-in formats whose feature is disabled
+//! - Configs fetched over HTTP with `#[config(remote = "...")]`, cached with their ETag and falling back to the cache or local file when offline (`remote` feature)
+//! - `tracing` spans for loads, saves, migrations, remote fetches and environment overrides, with secret values redacted
+//! - Config files rendered from `<name>.toml.j2` minijinja templates (`templates` feature)
+//! - Isolated per-test configs directories, fixtures and assertions (`testing` feature)
+//! - Randomized round-trip checks of config types through every enabled format (`testing` feature)
+//! - C ABI with a C header for non-Rust tooling (`ffi` feature)
+//! - Opt-in process-wide shared instance with `#[config(global)]`
+//! - Compile-time errors for misspelled `#[config(...)]` keys, unknown formats and formats whose feature is disabled
 //!
 //! # Example
 //!
@@ -36,13 +58,50 @@
 //! let loaded_config = AppConfig::load_or_default();
 //! ```
 
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-// Re-export the derive macro
-pub use mtc_config_derive::Configuration;
+mod diff;
+pub mod editor;
+pub mod encryption;
+pub mod environment;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod format;
+mod global;
+mod hex;
+mod history;
+pub mod integrity;
+pub mod lint;
+mod registry;
+pub mod remote;
+mod schema;
+mod telemetry;
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod types;
+
+pub use diff::{ChangeKind, ConfigChange, ConfigDiff};
+pub use format::{ConfigCodec, ConfigFormat, FormatRegistry};
+pub use global::{GlobalConfig, GlobalOverride};
+pub use history::{CURRENT_VERSION, ConfigVersion};
+pub use lint::lint_dir;
+pub use registry::{RegisteredConfig, find_registered_config, registered_configs};
+pub use schema::{ConfigSchema, ConfigSection, FieldSchema, ValidationRule, ValueFormat};
+// Used by the derive macro to describe value types
+#[doc(hidden)]
+pub use schema::{FormatOf, HasFormat, NoFormat};
+// Used by `ConfigSection` and the derive macro for default values
+pub use serde_json;
+// Used by the derive macro to register configuration types
+#[doc(hidden)]
+pub use inventory;
+// Re-export the derive macros
+pub use mtc_config_derive::{ConfigSection, Configuration};
 pub use serde::{*};
 /// Errors that can occur when working with configurations.
 ///
@@ -66,94 +125,501 @@ pub enum ConfigError {
     /// The configuration file was not found at the specified path.
     #[error("Configuration file not found at: {0}")]
     NotFound(PathBuf),
+
+    /// The given string does not name a known configuration format.
+    #[error("Unknown configuration format: {0}")]
+    UnknownFormat(String),
+
+    /// No backup with the given version id exists in the configuration history.
+    #[error("Configuration version not found: {0}")]
+    VersionNotFound(String),
+
+    /// The configuration violates the validation rules of its schema.
+    #[error("Invalid configuration: {0}")]
+    Validation(String),
+
+    /// The configuration could not be encrypted or decrypted.
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    /// The configuration file does not match its checksum sidecar, it was
+    /// edited outside of `save()` or the sidecar is missing.
+    #[error("Configuration file was modified outside of save(): {}", .0.display())]
+    IntegrityMismatch(PathBuf),
+
+    /// A configuration template could not be rendered.
+    #[error("Template error: {0}")]
+    Template(String),
+
+    /// A remote configuration could not be fetched and no cached copy exists.
+    #[error("Remote config error: {0}")]
+    Remote(String),
+
+    /// An argument is null or malformed, e.g. one passed through the C ABI
+    /// or a value parsed from a string.
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// File extensions `load()` looks for, in order, when the file for the declared
+/// format does not exist.
+pub const FALLBACK_EXTENSIONS: [&str; 8] =
+    ["toml", "yaml", "yml", "json", "ron", "json5", "ini", "env"];
+
+/// Gets the extensions `load()` looks for with their format: the
+/// [`FALLBACK_EXTENSIONS`], then those of the custom formats in the
+/// [`FormatRegistry`].
+fn fallback_formats() -> Vec<(&'static str, ConfigFormat)> {
+    let builtins = FALLBACK_EXTENSIONS
+        .iter()
+        .filter_map(|extension| Some((*extension, extension.parse().ok()?)));
+    let custom = FormatRegistry::formats()
+        .into_iter()
+        .filter(|format| matches!(format, ConfigFormat::Custom(_)))
+        .map(|format| (format.extension(), format));
+    builtins.chain(custom).collect()
 }
 
-/// Supported formats for configuration serialization and deserialization.
+/// Reads a whole configuration file into a string.
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    let mut file = File::open(path).map_err(ConfigError::Io)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(ConfigError::Io)?;
+    Ok(content)
+}
+
+/// Reads a configuration file of `T`, decrypting it if it is encrypted.
+fn read_config<T: Configuration>(path: &Path) -> Result<String, ConfigError> {
+    let content = read_file(path)?;
+    if encryption::is_sealed(&content) {
+        encryption::open(&content, &T::encryption_key()?, &T::config_name())
+    } else {
+        Ok(content)
+    }
+}
+
+/// Encrypts serialized content of `T` if [`Configuration::encrypted`] is set.
+fn seal_config<T: Configuration>(content: &str) -> Result<String, ConfigError> {
+    if T::encrypted() {
+        encryption::seal(content, &T::encryption_key()?, &T::config_name())
+    } else {
+        Ok(content.to_string())
+    }
+}
+
+/// Deserializes the content of a file of `T` in `format`, taking the table
+/// of `T` if it shares the file with other types.
 ///
-/// This enum defines the available formats for storing configuration data.
-/// The actual support for each format depends on the enabled feature flags:
-/// - `json`: Enables JSON format support
-/// - `toml`: Enables TOML format support (default)
-/// - `yaml`: Enables YAML format support
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ConfigFormat {
-    /// JSON format (requires the "json" feature)
-    Json,
-    /// TOML format (requires the "toml" feature)
-    Toml,
-    /// YAML format (requires the "yaml" feature)
-    Yaml,
+/// # Returns
+/// The configuration, or None if the file has no table for `T`.
+pub(crate) fn deserialize_config<T: Configuration>(
+    format: ConfigFormat,
+    content: &str,
+) -> Result<Option<T>, ConfigError> {
+    let Some(section) = T::section() else {
+        return format.deserialize(content).map(Some);
+    };
+    let mut document: serde_json::Value = format.deserialize(content)?;
+    match document.get_mut(&section).map(serde_json::Value::take) {
+        Some(table) => serde_json::from_value(table)
+            .map(Some)
+            .map_err(|e| ConfigError::Deserialization(format!("table `{section}`: {e}"))),
+        None => Ok(None),
+    }
 }
 
-impl ToString for ConfigFormat {
-    /// Converts the format to its string representation.
-    ///
-    /// # Returns
-    /// A string representing the format: "json", "toml", or "yaml".
-    fn to_string(&self) -> String {
-        match self {
-            ConfigFormat::Json => "json".to_string(),
-            ConfigFormat::Toml => "toml".to_string(),
-            ConfigFormat::Yaml => "yaml".to_string(),
+/// Reads and deserializes the file of `T` at `path`.
+///
+/// # Errors
+/// - `ConfigError::NotFound`: If the file shared with other types has no table for `T`
+fn read_config_file<T: Configuration>(path: &Path, format: ConfigFormat) -> Result<T, ConfigError> {
+    deserialize_config(format, &read_config::<T>(path)?)?
+        .ok_or_else(|| ConfigError::NotFound(path.to_path_buf()))
+}
+
+/// Puts the serialized content of `T` into its table of the file at `path`,
+/// keeping the tables of the other types sharing the file.
+///
+/// # Returns
+/// The content of the whole file.
+fn merge_section<T: Configuration>(
+    path: &Path,
+    section: &str,
+    content: &str,
+) -> Result<String, ConfigError> {
+    let format = T::format();
+    let mut document = if path.exists() {
+        format.deserialize(&read_config::<T>(path)?)?
+    } else {
+        serde_json::Value::Object(Default::default())
+    };
+    let mut table: serde_json::Value = format.deserialize(content)?;
+    // Formats like TOML have no null, leave absent values out instead
+    if let serde_json::Value::Object(map) = &mut table {
+        map.retain(|_, value| !value.is_null());
+    }
+    match &mut document {
+        serde_json::Value::Object(map) => {
+            map.insert(section.to_string(), table);
+        }
+        _ => {
+            return Err(ConfigError::Deserialization(format!(
+                "{} is not a table, can't add `{section}` to it",
+                path.display()
+            )));
         }
     }
+    format.serialize(&document)
 }
 
-impl From<String> for ConfigFormat {
-    /// Creates a ConfigFormat from a string.
-    ///
-    /// # Arguments
-    /// * `value` - A string representing the format: "json", "toml", or "yaml".
-    ///
-    /// # Returns
-    /// The corresponding ConfigFormat, or ConfigFormat::Toml for unrecognized formats.
-    fn from(value: String) -> Self {
-        match value.as_str() {
-            "json" => ConfigFormat::Json,
-            "toml" => ConfigFormat::Toml,
-            "yaml" => ConfigFormat::Yaml,
-            _ => ConfigFormat::Toml,
+/// Writes serialized content to the default path of `T`, backing up the
+/// previous file first when [`Configuration::history_limit`] is set.
+///
+/// Nothing is backed up if the content is unchanged. The content is
+/// encrypted first when [`Configuration::encrypted`] is set, and put into
+/// the table of `T` when it shares the file with other types.
+fn write_with_history<T: Configuration>(content: &str) -> Result<(), ConfigError> {
+    write_file_with_history::<T>(&T::default_path(), T::format(), content)
+}
+
+/// Like [`write_with_history`], but writes to `path` in `format`, e.g. the
+/// file `convert_to` creates.
+fn write_file_with_history<T: Configuration>(
+    path: &Path,
+    format: ConfigFormat,
+    content: &str,
+) -> Result<(), ConfigError> {
+    // Create parent directories if they don't exist
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(ConfigError::Io)?;
+    }
+
+    let merged;
+    let content = match T::section() {
+        Some(section) => {
+            merged = merge_section::<T>(path, &section, content)?;
+            merged.as_str()
         }
+        None => content,
+    };
+
+    // Compare plain text, encrypting the same content twice gives different files
+    if T::history_limit() > 0 && path.exists() && read_config::<T>(path)? != content {
+        history::archive(path, format, &T::history_dir(), T::history_limit())?;
     }
+
+    write_config_file::<T>(path, content)
 }
 
-impl From<&str> for ConfigFormat {
-    /// Creates a ConfigFormat from a string slice.
-    ///
-    /// # Arguments
-    /// * `value` - A string slice representing the format: "json", "toml", or "yaml".
-    ///
-    /// # Returns
-    /// The corresponding ConfigFormat, or ConfigFormat::Toml for unrecognized formats.
-    fn from(value: &str) -> Self {
-        match value {
-            "json" => ConfigFormat::Json,
-            "toml" => ConfigFormat::Toml,
-            "yaml" => ConfigFormat::Yaml,
-            _ => ConfigFormat::Toml,
+/// Writes serialized content of `T` to `path`, encrypting it and writing its
+/// checksum sidecar as configured.
+fn write_config_file<T: Configuration>(path: &Path, content: &str) -> Result<(), ConfigError> {
+    let integrity_key = if T::integrity() {
+        Some(T::integrity_key()?)
+    } else {
+        None
+    };
+    write_sealed_file(
+        path,
+        &seal_config::<T>(content)?,
+        integrity_key.as_ref().map(Option::as_deref),
+    )
+}
+
+/// Writes content, already encrypted if needed, to `path`.
+///
+/// With `integrity` set to the HMAC key, or None for a plain checksum, the
+/// checksum sidecar is written first. Both go to temporary files renamed
+/// over the originals, so readers never see a partially written file.
+pub(crate) fn write_sealed_file(
+    path: &Path,
+    content: &str,
+    integrity: Option<Option<&[u8]>>,
+) -> Result<(), ConfigError> {
+    if let Some(key) = integrity {
+        integrity::seal_content(path, content.as_bytes(), key)?;
+    }
+    write_atomic(path, content.as_bytes())
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it over `path`.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<(), ConfigError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = File::create(&temporary).map_err(ConfigError::Io)?;
+    file.write_all(content)
+        .and_then(|()| file.sync_all())
+        .map_err(ConfigError::Io)?;
+    fs::rename(&temporary, path).map_err(ConfigError::Io)
+}
+
+/// Loads `T` like [`Configuration::load`], without the environment overrides.
+pub(crate) fn load_saved<T: Configuration>() -> Result<T, ConfigError> {
+    if let Some(source) = remote::RemoteSource::of::<T>() {
+        match load_remote_document::<T>(&source) {
+            Ok(config) => return Ok(config),
+            // The local file is the last fallback
+            Err(e) if T::find_existing().is_none() && !T::template_path().exists() => {
+                return Err(e);
+            }
+            Err(e) => tracing::warn!(
+                config = %T::config_name(),
+                error = telemetry::redacted_error::<T>(&e),
+                "remote config unavailable, using the local file"
+            ),
+        }
+    }
+    let Some((path, format)) = T::find_existing() else {
+        if let Some(config) = migrate_standalone_file::<T>()? {
+            return Ok(config);
         }
+        if T::template_path().exists() {
+            return parse_rendered(&T::render_template()?);
+        }
+        return Err(ConfigError::NotFound(T::default_path()));
+    };
+
+    if T::integrity() {
+        integrity::verify(&path, T::integrity_key()?.as_deref())?;
     }
+    let config: T = match read_config_file(&path, format) {
+        Err(ConfigError::NotFound(missing)) => {
+            return migrate_standalone_file::<T>()?.ok_or(ConfigError::NotFound(missing));
+        }
+        result => result?,
+    };
+    config.validate()?;
+
+    if path != T::default_path() && T::migrate_on_load() {
+        tracing::info!(
+            config = %T::config_name(),
+            from = %path.display(),
+            to = %T::default_path().display(),
+            "migrating config file to the declared format"
+        );
+        config.save()?;
+        remove_config_file(&path)?;
+    }
+
+    Ok(config)
 }
 
-impl Default for ConfigFormat {
-    /// Returns the default configuration format (TOML).
-    ///
-    /// # Returns
-    /// ConfigFormat::Toml as the default format.
-    fn default() -> Self {
-        // Default to TOML as the standard configuration format
-        ConfigFormat::Toml
+/// Moves the standalone file a configuration had before it was declared
+/// with `section`, e.g. `mkbook.toml` for `[mkbook]` in `workspace.toml`,
+/// into its table of the shared file.
+///
+/// # Returns
+/// The migrated configuration, or None if `T` has no section or no
+/// standalone file.
+fn migrate_standalone_file<T: Configuration>() -> Result<Option<T>, ConfigError> {
+    if T::section().is_none() {
+        return Ok(None);
+    }
+    let format = T::format();
+    let path = get_configs_dir().join(format!("{}.{}", T::config_name(), format.extension()));
+    if path == T::default_path() || !path.exists() {
+        return Ok(None);
+    }
+
+    let config: T = format.deserialize(&read_config::<T>(&path)?)?;
+    config.validate()?;
+    tracing::info!(
+        config = %T::config_name(),
+        from = %path.display(),
+        to = %T::default_path().display(),
+        "migrating standalone config file into its table of the shared file"
+    );
+    config.save()?;
+    remove_config_file(&path)?;
+    Ok(Some(config))
+}
+
+/// Checks that `T` is declared with `#[config(multi)]`.
+fn check_multi<T: Configuration>() -> Result<(), ConfigError> {
+    if T::multi() {
+        return Ok(());
+    }
+    Err(ConfigError::InvalidArgument(format!(
+        "`{}` has no instances, declare it with #[config(multi)]",
+        T::config_name()
+    )))
+}
+
+/// Checks that `T` is declared with `#[config(multi)]` and `id` can name an
+/// instance file.
+fn check_instance<T: Configuration>(id: &str) -> Result<(), ConfigError> {
+    check_multi::<T>()?;
+    check_file_stem("instance id", id)
+}
+
+/// Checks that `stem` can name a file in the configs directory without
+/// leaving it, e.g. through `..` or `/`.
+pub(crate) fn check_file_stem(what: &str, stem: &str) -> Result<(), ConfigError> {
+    let valid = !stem.is_empty()
+        && stem
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(ConfigError::InvalidArgument(format!(
+            "invalid {what} `{stem}`, expected ASCII letters, digits, `-` and `_`"
+        )));
     }
+    Ok(())
+}
+
+/// Fetches `T` from `source`, using the cached copy if the server can't be
+/// used or sends a document that doesn't parse, and caches valid documents.
+fn load_remote_document<T: Configuration>(source: &remote::RemoteSource) -> Result<T, ConfigError> {
+    let parse = |document: &remote::RemoteDocument| -> Result<T, ConfigError> {
+        let config: T = deserialize_config(T::format(), &document.content)?.ok_or_else(|| {
+            ConfigError::Remote(format!(
+                "{} has no table for `{}`",
+                source.url(),
+                T::config_name()
+            ))
+        })?;
+        config.validate()?;
+        Ok(config)
+    };
+    telemetry::operation::<T, _>("load_remote", source.cache_path(), || {
+        let document = source.fetch()?;
+        let url = telemetry::redacted_url(source.url());
+        match &document.error {
+            Some(error) => tracing::warn!(
+                url,
+                error,
+                "remote config unavailable, using the cached copy"
+            ),
+            None => {
+                tracing::debug!(url, origin = ?document.origin, etag = ?document.etag, "remote config fetched")
+            }
+        }
+        match parse(&document) {
+            Ok(config) => {
+                source.store(&document)?;
+                Ok(config)
+            }
+            Err(e) if document.origin == remote::RemoteOrigin::Fetched => match source.cached() {
+                Some(cached) => {
+                    tracing::warn!(
+                        url,
+                        error = telemetry::redacted_error::<T>(&e),
+                        "remote config is invalid, using the cached copy"
+                    );
+                    parse(&cached)
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    })
+}
+
+/// Applies the environment overrides of `T`, if it declares a prefix, and
+/// validates the result.
+fn with_env_overrides<T: Configuration>(config: T) -> Result<T, ConfigError> {
+    let Some(prefix) = T::env_prefix() else {
+        return Ok(config);
+    };
+    let overridden = config.with_env(&prefix, env_vars())?;
+    overridden.validate()?;
+    telemetry::env_overrides(&prefix, &config, &overridden);
+    Ok(overridden)
+}
+
+/// Applies the environment overrides of `T` to a freshly created default,
+/// keeping the default with a warning if they don't apply.
+fn env_overrides_or_default<T: Configuration>(config: T) -> T {
+    let Some(prefix) = T::env_prefix() else {
+        return config;
+    };
+    match config
+        .with_env(&prefix, env_vars())
+        .and_then(|overridden| overridden.validate().map(|()| overridden))
+    {
+        Ok(overridden) => {
+            telemetry::env_overrides(&prefix, &config, &overridden);
+            overridden
+        }
+        Err(e) => {
+            tracing::warn!(
+                config = %T::config_name(),
+                prefix,
+                error = telemetry::redacted_error::<T>(&e),
+                "failed to apply environment overrides, using the default"
+            );
+            config
+        }
+    }
+}
+
+/// Parses a rendered template of `T` in its declared format and validates it.
+fn parse_rendered<T: Configuration>(rendered: &str) -> Result<T, ConfigError> {
+    let config: T = deserialize_config(T::format(), rendered)?
+        .ok_or_else(|| ConfigError::NotFound(T::template_path()))?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Removes a configuration file along with its checksum sidecar.
+fn remove_config_file(path: &Path) -> Result<(), ConfigError> {
+    fs::remove_file(path).map_err(ConfigError::Io)?;
+    let sidecar = integrity::sidecar_path(path);
+    if sidecar.exists() {
+        fs::remove_file(sidecar).map_err(ConfigError::Io)?;
+    }
+    Ok(())
+}
+
+thread_local! {
+    /// Configs directory of the current thread, set by `testing::TestConfigRoot`.
+    static CONFIG_ROOT_OVERRIDE: std::cell::RefCell<Option<PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+
+    /// Environment variables of the current thread, set by
+    /// `testing::TestConfigRoot::set_env` without touching the process
+    /// environment, or None where `remove_env` hides a process variable.
+    static ENV_OVERRIDE: std::cell::RefCell<BTreeMap<String, Option<String>>> =
+        const { std::cell::RefCell::new(BTreeMap::new()) };
+}
+
+/// Reads an environment variable; one set or removed for the current thread
+/// by a test takes precedence.
+pub(crate) fn env_var(name: &str) -> Option<String> {
+    match ENV_OVERRIDE.with(|vars| vars.borrow().get(name).cloned()) {
+        Some(value) => value,
+        None => std::env::var(name).ok(),
+    }
+}
+
+/// Lists the environment variables, including those set and without those
+/// removed for the current thread by a test.
+pub(crate) fn env_vars() -> Vec<(String, String)> {
+    let mut vars: BTreeMap<String, String> = std::env::vars().collect();
+    ENV_OVERRIDE.with(|overrides| {
+        for (name, value) in overrides.borrow().iter() {
+            match value {
+                Some(value) => vars.insert(name.clone(), value.clone()),
+                None => vars.remove(name),
+            };
+        }
+    });
+    vars.into_iter().collect()
 }
 
 /// Gets the configs directory in the workspace root.
 ///
 /// This function returns the path to the "configs" directory
 /// within the workspace root, where configuration files should be stored.
+/// A directory set for the current thread by a test takes precedence.
 ///
 /// # Returns
 /// A PathBuf pointing to the configs directory.
 pub fn get_configs_dir() -> PathBuf {
+    if let Some(dir) = CONFIG_ROOT_OVERRIDE.with(|root| root.borrow().clone()) {
+        return dir;
+    }
     let storage_dir = std::env::var("CONFIG_ROOT_DIR").unwrap_or_else(|_| {
         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("configs")
@@ -167,8 +633,9 @@ pub fn get_configs_dir() -> PathBuf {
 
 /// The main configuration trait that provides save, load, and load_or_default methods.
 ///
-/// This trait can be derived for any struct that implements `serde::Serialize`,
-/// `serde::Deserialize`, and `Default`. It provides methods for saving and loading
+/// This trait can be derived for any struct, newtype wrapper or enum that
+/// implements `serde::Serialize`, `serde::Deserialize`, and `Default`,
+/// including generic ones. It provides methods for saving and loading
 /// configuration data to and from files in various formats.
 ///
 /// # Type Requirements
@@ -180,34 +647,74 @@ pub trait Configuration: Serialize + for<'de> Deserialize<'de> + Default + Sized
     /// Gets the name for this configuration.
     ///
     /// This name is used to generate the filename for the configuration file.
-    /// By default, the derive macro uses the lowercase name of the struct.
+    /// By default, the derive macro uses the snake_case name of the type,
+    /// e.g. `http_server_config` for `HttpServerConfig`.
     ///
     /// # Returns
     /// A string representing the configuration name.
     fn config_name() -> String;
 
+    /// Gets the name of the file this configuration is stored in, without
+    /// its extension.
+    ///
+    /// By default, this returns the configuration name. It can be overridden
+    /// by implementing this method or using the #[config(file = "...")]
+    /// attribute, together with `section`.
+    ///
+    /// # Returns
+    /// The file name, e.g. `workspace` for `workspace.toml`.
+    fn file_name() -> String {
+        Self::config_name()
+    }
+
+    /// Gets the table holding this configuration in a file shared with other types.
+    ///
+    /// By default, this returns None and the configuration is the whole file.
+    /// It can be overridden by implementing this method or using the
+    /// #[config(section = "...")] attribute. `load()` reads only this table
+    /// and `save()` replaces only this table, keeping the others; comments
+    /// in the file are not kept. Without the table, `load()` moves a
+    /// standalone `<name>.<ext>` file from before the type was declared with
+    /// a section into it, and reports `NotFound` if there is none.
+    ///
+    /// # Returns
+    /// The table name, e.g. `mkbook` for `[mkbook]` in `workspace.toml`.
+    fn section() -> Option<String> {
+        None
+    }
+
     /// Gets the default path for this configuration file.
     ///
     /// This function generates the full path to the configuration file
-    /// by combining the configs directory with the configuration name
+    /// by combining the configs directory with the [`Configuration::file_name`]
     /// and appropriate file extension based on the format.
     ///
     /// # Returns
     /// A PathBuf pointing to the default configuration file location.
     fn default_path() -> PathBuf {
-        let extension = match Self::format() {
-            ConfigFormat::Json => "json",
-            ConfigFormat::Toml => "toml",
-            ConfigFormat::Yaml => "yaml",
-        };
+        get_configs_dir().join(format!(
+            "{}.{}",
+            Self::file_name(),
+            Self::format().extension()
+        ))
+    }
 
-        get_configs_dir().join(format!("{}.{}", Self::config_name(), extension))
+    /// Gets the path this configuration would have if it was stored in `format`.
+    ///
+    /// This is the default path with its extension replaced by the one of
+    /// the given format.
+    ///
+    /// # Returns
+    /// A PathBuf pointing to the configuration file location for `format`.
+    fn path_for(format: ConfigFormat) -> PathBuf {
+        Self::default_path().with_extension(format.extension())
     }
 
     /// Gets the serialization format for this configuration.
     ///
     /// By default, this returns TOML, but it can be overridden by
-    /// implementing this method or using the #[config(format = "...")] attribute.
+    /// implementing this method or using the #[config(format = "...")] attribute,
+    /// or #[config(codec = "...")] for a codec registered in the [`FormatRegistry`].
     ///
     /// # Returns
     /// The ConfigFormat to use for this configuration.
@@ -215,65 +722,557 @@ pub trait Configuration: Serialize + for<'de> Deserialize<'de> + Default + Sized
         ConfigFormat::default()
     }
 
-    /// Saves the configuration to the default path.
+    /// Whether `load()` should rewrite a file found in another format into the declared one.
     ///
-    /// This method serializes the configuration to the specified format
-    /// and saves it to the default path. It creates any necessary parent
-    /// directories if they don't exist.
+    /// By default, this returns false and the file found is left untouched.
+    /// It can be overridden by implementing this method or using the
+    /// #[config(migrate)] attribute.
     ///
     /// # Returns
-    /// A Result indicating success or providing an error if the save failed.
+    /// true if files in other formats should be migrated on load.
+    fn migrate_on_load() -> bool {
+        false
+    }
+
+    /// Gets the number of previous versions kept when saving.
+    ///
+    /// By default, this returns 0 and `save()` overwrites the previous file.
+    /// It can be overridden by implementing this method or using the
+    /// #[config(history = N)] attribute.
+    ///
+    /// # Returns
+    /// The maximum number of backups kept in [`Configuration::history_dir`].
+    fn history_limit() -> usize {
+        0
+    }
+
+    /// Whether this configuration is stored as several named instances.
+    ///
+    /// By default, this returns false. It can be overridden by implementing
+    /// this method or using the #[config(multi)] attribute, which enables
+    /// [`Configuration::load_instance`] and the other instance methods.
+    ///
+    /// # Returns
+    /// true if instances are stored in [`Configuration::instances_dir`].
+    fn multi() -> bool {
+        false
+    }
+
+    /// Gets the URL this configuration is fetched from, see [`remote`].
+    ///
+    /// By default, this returns None and `load()` only reads local files.
+    /// It can be overridden by implementing this method or using the
+    /// `#[config(remote = "...")]` attribute, which requires the `remote`
+    /// feature.
+    ///
+    /// # Returns
+    /// The URL, where `$VAR` and `${VAR}` are read from the environment.
+    fn remote_url() -> Option<String> {
+        None
+    }
+
+    /// Loads this configuration from its remote URL or the cached copy,
+    /// without falling back to the local file, see [`remote`].
     ///
     /// # Errors
-    /// - `ConfigError::Io`: If there was an I/O error creating the file or directories
-    /// - `ConfigError::Serialization`: If there was an error serializing the configuration
-    fn save(&self) -> Result<(), ConfigError> {
-        let path = Self::default_path();
+    /// - `ConfigError::InvalidArgument`: If the type has no remote URL
+    /// - `ConfigError::Remote`: If the server can't be used and nothing is cached
+    /// - `ConfigError::Deserialization`: If the document could not be parsed
+    /// - `ConfigError::Validation`: If the document violates the validation rules
+    fn load_remote() -> Result<Self, ConfigError> {
+        let source = remote::RemoteSource::of::<Self>().ok_or_else(|| {
+            ConfigError::InvalidArgument(format!(
+                "`{}` has no remote URL, declare it with #[config(remote = \"...\")]",
+                Self::config_name()
+            ))
+        })?;
+        with_env_overrides(load_remote_document::<Self>(&source)?)
+    }
+
+    /// Gets the prefix of the environment variables overriding this configuration.
+    ///
+    /// By default, this returns None and `load()` ignores the environment.
+    /// It can be overridden by implementing this method or using the
+    /// `#[config(env_prefix = "...")]` attribute, see [`environment`].
+    ///
+    /// # Returns
+    /// The prefix, e.g. `APP` for `APP_SERVER__PORT`.
+    fn env_prefix() -> Option<String> {
+        None
+    }
+
+    /// Flattens this configuration into environment variables, see [`environment`].
+    ///
+    /// # Returns
+    /// `(name, value)` pairs like `("APP_SERVER__PORT", "8080")`, which
+    /// [`Configuration::with_env`] reads back into the same configuration.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the configuration can't be serialized
+    fn to_env(&self, prefix: &str) -> Result<Vec<(String, String)>, ConfigError> {
+        let value =
+            serde_json::to_value(self).map_err(|e| ConfigError::Serialization(e.to_string()))?;
+        Ok(environment::flatten(&value, prefix))
+    }
+
+    /// Overrides values of this configuration with the environment variables
+    /// starting with `prefix`, see [`environment`].
+    ///
+    /// Pass `std::env::vars()` to read the process environment.
+    ///
+    /// # Returns
+    /// The configuration with the overrides applied.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the configuration can't be serialized
+    /// - `ConfigError::Deserialization`: If an override doesn't fit the value it replaces
+    /// - `ConfigError::InvalidArgument`: If the prefix is empty or a variable goes
+    ///   through a value that is not a table
+    fn with_env(
+        &self,
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut value =
+            serde_json::to_value(self).map_err(|e| ConfigError::Serialization(e.to_string()))?;
+        let guessed = environment::apply(&mut value, prefix, vars)?;
+        match serde_json::from_value(value.clone()) {
+            Ok(config) => Ok(config),
+            // Values without one to replace were parsed as JSON, they may be strings
+            Err(_) if !guessed.is_empty() => {
+                for (path, raw) in &guessed {
+                    environment::restore_string(&mut value, path, raw);
+                }
+                serde_json::from_value(value)
+                    .map_err(|e| ConfigError::Deserialization(e.to_string()))
+            }
+            Err(e) => Err(ConfigError::Deserialization(e.to_string())),
+        }
+    }
+
+    /// Describes the fields of this configuration.
+    ///
+    /// The derive macro lists every named field with its type, doc comment,
+    /// default value, validation rules and secret flag. By default, and for
+    /// enums and newtypes, the schema has no fields.
+    ///
+    /// # Returns
+    /// The [`ConfigSchema`] of this configuration.
+    fn schema() -> ConfigSchema {
+        ConfigSchema::new(std::any::type_name::<Self>(), "")
+    }
+
+    /// Checks the configuration against the validation rules of its schema.
+    ///
+    /// `load()` calls this on every loaded configuration.
+    ///
+    /// # Errors
+    /// - `ConfigError::Validation`: If a rule is violated
+    /// - `ConfigError::Serialization`: If the configuration can't be converted into a value tree
+    fn validate(&self) -> Result<(), ConfigError> {
+        let schema = Self::schema();
+        if !schema.has_rules() {
+            return Ok(());
+        }
+        let value =
+            serde_json::to_value(self).map_err(|e| ConfigError::Serialization(e.to_string()))?;
+        schema.validate(&value)
+    }
+
+    /// Whether `save()` encrypts the configuration file.
+    ///
+    /// By default, this returns false. It can be overridden by implementing
+    /// this method or using the #[config(encrypted)] attribute, which
+    /// requires the `encryption` feature. Encrypted files are decrypted by
+    /// `load()` regardless of this setting, see [`encryption`].
+    ///
+    /// # Returns
+    /// true if the configuration is encrypted at rest.
+    fn encrypted() -> bool {
+        false
+    }
 
-        // Create parent directories if they don't exist
+    /// Gets the key used to encrypt and decrypt this configuration.
+    ///
+    /// By default, the key is read by [`encryption::load_key`] from the
+    /// environment.
+    ///
+    /// # Errors
+    /// - `ConfigError::Encryption`: If no valid key is available
+    /// - `ConfigError::Io`: If the key file can't be read
+    fn encryption_key() -> Result<[u8; 32], ConfigError> {
+        encryption::load_key()
+    }
+
+    /// Whether `save()` writes a checksum sidecar that `load()` verifies.
+    ///
+    /// By default, this returns false. It can be overridden by implementing
+    /// this method or using the #[config(integrity)] attribute, which
+    /// requires the `integrity` feature. See [`integrity`].
+    ///
+    /// # Returns
+    /// true if the configuration file is checked for outside edits.
+    fn integrity() -> bool {
+        false
+    }
+
+    /// Gets the HMAC key of the checksum sidecar.
+    ///
+    /// By default, the key is read by [`integrity::load_key`] from the
+    /// environment; without a key a plain SHA-256 checksum is used.
+    ///
+    /// # Errors
+    /// - `ConfigError::Io`: If the key file can't be read
+    fn integrity_key() -> Result<Option<Vec<u8>>, ConfigError> {
+        integrity::load_key()
+    }
+
+    /// Checks the existing configuration file against its checksum sidecar.
+    ///
+    /// # Errors
+    /// - `ConfigError::NotFound`: If no configuration file exists
+    /// - `ConfigError::IntegrityMismatch`: If the file was edited outside of `save()`
+    /// - `ConfigError::Io`: If there was an I/O error reading a file
+    fn verify_integrity() -> Result<(), ConfigError> {
+        let (path, _) =
+            Self::find_existing().ok_or_else(|| ConfigError::NotFound(Self::default_path()))?;
+        integrity::verify(&path, Self::integrity_key()?.as_deref())
+    }
+
+    /// Accepts the existing configuration file as it is after a manual edit,
+    /// rewriting its checksum sidecar.
+    ///
+    /// The file must still deserialize and pass validation, a broken edit
+    /// is not accepted.
+    ///
+    /// # Errors
+    /// - `ConfigError::NotFound`: If no configuration file exists
+    /// - `ConfigError::Io`: If there was an I/O error reading or writing a file
+    /// - `ConfigError::Deserialization`: If the file could not be parsed
+    /// - `ConfigError::Validation`: If the file violates the validation rules
+    fn reseal() -> Result<(), ConfigError> {
+        let (path, format) =
+            Self::find_existing().ok_or_else(|| ConfigError::NotFound(Self::default_path()))?;
+        let config: Self = read_config_file(&path, format)?;
+        config.validate()?;
+        integrity::seal(&path, Self::integrity_key()?.as_deref())
+    }
+
+    /// Gets the default file embedded with `#[config(default_file = "...")]`.
+    ///
+    /// By default, this returns None and `load_or_default()` falls back to
+    /// [`Default`]. The derive macro embeds the file with `include_str!`,
+    /// resolving the path relative to the crate's `Cargo.toml`, and takes the
+    /// format from its extension.
+    ///
+    /// # Returns
+    /// The format and content of the embedded file, if there is one.
+    fn default_file() -> Option<(ConfigFormat, &'static str)> {
+        None
+    }
+
+    /// Parses the embedded default file.
+    ///
+    /// The derive macro generates a test calling this, so a default file that
+    /// no longer matches the type fails `cargo test`.
+    ///
+    /// # Returns
+    /// The parsed configuration, or None if no default file is embedded.
+    ///
+    /// # Errors
+    /// - `ConfigError::Deserialization`: If the file doesn't deserialize into this type
+    /// - `ConfigError::Validation`: If the file violates the validation rules
+    fn embedded_default() -> Result<Option<Self>, ConfigError> {
+        let Some((format, content)) = Self::default_file() else {
+            return Ok(None);
+        };
+        let config: Self = format.deserialize(content)?;
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// Gets the path of the template rendering this configuration.
+    ///
+    /// # Returns
+    /// The default path with `.j2` appended, e.g. `server.toml.j2`.
+    fn template_path() -> PathBuf {
+        template::template_path(&Self::default_path())
+    }
+
+    /// Renders the template of this configuration, see [`template`].
+    ///
+    /// # Returns
+    /// The rendered text, in the declared format.
+    ///
+    /// # Errors
+    /// - `ConfigError::NotFound`: If the template doesn't exist
+    /// - `ConfigError::Io`: If there was an I/O error reading the template
+    /// - `ConfigError::Template`: If the template can't be rendered
+    fn render_template() -> Result<String, ConfigError> {
+        let path = Self::template_path();
+        if !path.exists() {
+            return Err(ConfigError::NotFound(path));
+        }
+        template::render(&Self::config_name(), &read_file(&path)?)
+    }
+
+    /// Renders the template and saves the result as the configuration file,
+    /// encrypted and sealed as configured.
+    ///
+    /// `load()` then reads the saved file and no longer renders the template.
+    ///
+    /// # Returns
+    /// The rendered configuration.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::render_template`], plus
+    /// - `ConfigError::Deserialization`: If the rendered text doesn't deserialize into this type
+    /// - `ConfigError::Validation`: If the rendered configuration violates the validation rules
+    fn materialize() -> Result<Self, ConfigError> {
+        let rendered = Self::render_template()?;
+        let config = parse_rendered::<Self>(&rendered)?;
+        if Self::section().is_some() {
+            // The template renders the whole shared file, only the table of this type is saved
+            config.save()?;
+        } else {
+            write_with_history::<Self>(&rendered)?;
+        }
+        Ok(config)
+    }
+
+    /// Renders the template into a plain file outside the configs directory,
+    /// e.g. for a docker-compose or deployment directory.
+    ///
+    /// # Returns
+    /// The rendered configuration.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::materialize`].
+    fn render_to(path: &Path) -> Result<Self, ConfigError> {
+        let rendered = Self::render_template()?;
+        let config = parse_rendered::<Self>(&rendered)?;
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).map_err(ConfigError::Io)?;
         }
+        fs::write(path, rendered).map_err(ConfigError::Io)?;
+        Ok(config)
+    }
+
+    /// Gets the directory backups of this configuration are kept in.
+    ///
+    /// # Returns
+    /// A PathBuf pointing to `.history/<config name>` in the configs directory.
+    fn history_dir() -> PathBuf {
+        get_configs_dir().join(".history").join(Self::config_name())
+    }
+
+    /// Lists the backed up versions of this configuration, newest first.
+    ///
+    /// # Returns
+    /// The versions found in [`Configuration::history_dir`].
+    ///
+    /// # Errors
+    /// - `ConfigError::Io`: If there was an I/O error reading the history directory
+    fn history() -> Result<Vec<ConfigVersion>, ConfigError> {
+        history::list(&Self::history_dir())
+    }
+
+    /// Restores a backed up version as the current configuration.
+    ///
+    /// The current file is backed up first, so a restore can be undone
+    /// like any other save.
+    ///
+    /// # Returns
+    /// The restored configuration.
+    ///
+    /// # Errors
+    /// - `ConfigError::VersionNotFound`: If there is no backup with this id
+    /// - `ConfigError::Io`: If there was an I/O error reading or writing a file
+    /// - `ConfigError::Deserialization`: If the backup could not be parsed
+    /// - `ConfigError::Serialization`: If the backup had to be rewritten in the declared format and failed
+    fn restore(version: &str) -> Result<Self, ConfigError> {
+        telemetry::operation::<Self, _>("restore", &Self::default_path(), || {
+            let version = history::find(&Self::history_dir(), version)?;
+            let content = read_config::<Self>(&version.path)?;
+            let config: Self = deserialize_config(version.format, &content)?
+                .ok_or_else(|| ConfigError::NotFound(version.path.clone()))?;
+
+            if version.format == Self::format() && Self::section().is_none() {
+                // Keep the backup byte for byte, including comments
+                write_with_history::<Self>(&content)?;
+            } else {
+                config.save()?;
+            }
+            Ok(config)
+        })
+    }
+
+    /// Renders a line diff between two versions of this configuration.
+    ///
+    /// Either id may be [`CURRENT_VERSION`] to refer to the live file.
+    ///
+    /// # Returns
+    /// The diff, with removed lines prefixed by `-` and added lines by `+`.
+    ///
+    /// # Errors
+    /// - `ConfigError::VersionNotFound`: If there is no backup with one of the ids
+    /// - `ConfigError::NotFound`: If [`CURRENT_VERSION`] is used and no configuration file exists
+    /// - `ConfigError::Io`: If there was an I/O error reading a file
+    fn diff_versions(from: &str, to: &str) -> Result<String, ConfigError> {
+        let read_version = |id: &str| -> Result<String, ConfigError> {
+            if id == CURRENT_VERSION {
+                let (path, _) = Self::find_existing()
+                    .ok_or_else(|| ConfigError::NotFound(Self::default_path()))?;
+                read_config::<Self>(&path)
+            } else {
+                read_config::<Self>(&history::find(&Self::history_dir(), id)?.path)
+            }
+        };
+        Ok(history::line_diff(&read_version(from)?, &read_version(to)?))
+    }
+
+    /// Computes the structural differences from this configuration to `other`.
+    ///
+    /// # Returns
+    /// The changes, with `self` as the old and `other` as the new side.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If one of the configurations can't be serialized
+    fn diff(&self, other: &Self) -> Result<ConfigDiff, ConfigError> {
+        ConfigDiff::between(self, other)
+    }
+
+    /// Computes what `save()` would change in the saved configuration file.
+    ///
+    /// The saved file is the old side and this configuration the new one. If
+    /// no file exists yet every value is reported as added. The file is not
+    /// migrated, even if [`Configuration::migrate_on_load`] is set.
+    ///
+    /// # Errors
+    /// - `ConfigError::Io`: If there was an I/O error reading the file
+    /// - `ConfigError::Deserialization`: If the saved file could not be parsed
+    /// - `ConfigError::Serialization`: If one of the configurations can't be serialized
+    fn diff_saved(&self) -> Result<ConfigDiff, ConfigError> {
+        match Self::find_existing() {
+            Some((path, format)) => match read_config_file::<Self>(&path, format) {
+                Ok(saved) => saved.diff(self),
+                Err(ConfigError::NotFound(_)) => ConfigDiff::between(&(), self),
+                Err(e) => Err(e),
+            },
+            None => ConfigDiff::between(&(), self),
+        }
+    }
 
-        let mut file = File::create(&path).map_err(ConfigError::Io)?;
+    /// Computes the differences between the default configuration and this one.
+    ///
+    /// # Returns
+    /// The changes, with `Self::default()` as the old side.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If one of the configurations can't be serialized
+    fn diff_default(&self) -> Result<ConfigDiff, ConfigError> {
+        Self::default().diff(self)
+    }
+
+    /// Finds the file this configuration is currently stored in.
+    ///
+    /// The default path is checked first, then the same path with each of
+    /// the [`FALLBACK_EXTENSIONS`] and the extensions of registered custom formats.
+    ///
+    /// # Returns
+    /// The path of the existing file and the format it is stored in, or None
+    /// if no file exists.
+    fn find_existing() -> Option<(PathBuf, ConfigFormat)> {
+        let path = Self::default_path();
+        if path.exists() {
+            return Some((path, Self::format()));
+        }
+        fallback_formats()
+            .into_iter()
+            .find_map(|(extension, format)| {
+                let path = path.with_extension(extension);
+                path.exists().then_some((path, format))
+            })
+    }
 
-        match Self::format() {
-            #[cfg(feature = "json")]
-            ConfigFormat::Json => {
-                let content = serde_json::to_string_pretty(self)
-                    .map_err(|e| ConfigError::Serialization(e.to_string()))?;
-                file.write_all(content.as_bytes())
-                    .map_err(ConfigError::Io)?;
+    /// Rewrites the on-disk configuration file in another format.
+    ///
+    /// The existing file is located with [`Configuration::find_existing`],
+    /// parsed in its own format, written to [`Configuration::path_for`] the
+    /// target format and then removed. Like `save()`, the removed file and a
+    /// replaced one are backed up when [`Configuration::history_limit`] is set.
+    ///
+    /// # Returns
+    /// The path of the converted file.
+    ///
+    /// # Errors
+    /// - `ConfigError::NotFound`: If no configuration file exists
+    /// - `ConfigError::Io`: If there was an I/O error reading, writing or removing a file
+    /// - `ConfigError::Deserialization`: If the existing file could not be parsed
+    /// - `ConfigError::Serialization`: If the configuration can't be written in `format`
+    fn convert_to(format: ConfigFormat) -> Result<PathBuf, ConfigError> {
+        telemetry::operation::<Self, _>("convert", &Self::default_path(), || {
+            if let Some(section) = Self::section() {
+                return Err(ConfigError::InvalidArgument(format!(
+                    "`{}` is the `{section}` table of a shared file, which can't be converted on its own",
+                    Self::config_name()
+                )));
             }
-            #[cfg(feature = "toml")]
-            ConfigFormat::Toml => {
-                let content =
-                    toml::to_string(self).map_err(|e| ConfigError::Serialization(e.to_string()))?;
-                file.write_all(content.as_bytes())
-                    .map_err(ConfigError::Io)?;
+            let Some((path, current)) = Self::find_existing() else {
+                return Err(ConfigError::NotFound(Self::default_path()));
+            };
+            let target = Self::path_for(format);
+            if target == path {
+                return Ok(target);
             }
-            #[cfg(feature = "yaml")]
-            ConfigFormat::Yaml => {
-                let content = serde_yaml::to_string(self)
-                    .map_err(|e| ConfigError::Serialization(e.to_string()))?;
-                file.write_all(content.as_bytes())
-                    .map_err(ConfigError::Io)?;
+
+            if Self::integrity() {
+                integrity::verify(&path, Self::integrity_key()?.as_deref())?;
             }
-            #[allow(unreachable_patterns)]
-            _ => {
-                return Err(ConfigError::Serialization(
-                    "Selected format is not enabled".into(),
-                ));
+            let config: Self = current.deserialize(&read_config::<Self>(&path)?)?;
+            // The converted file is removed, keep it like a replaced one
+            if Self::history_limit() > 0 {
+                history::archive(&path, current, &Self::history_dir(), Self::history_limit())?;
             }
-        }
+            write_file_with_history::<Self>(&target, format, &format.serialize(&config)?)?;
+            remove_config_file(&path)?;
 
-        Ok(())
+            Ok(target)
+        })
+    }
+
+    /// Saves the configuration to the default path.
+    ///
+    /// This method serializes the configuration to the specified format
+    /// and saves it to the default path. It creates any necessary parent
+    /// directories if they don't exist, and backs up the previous file when
+    /// [`Configuration::history_limit`] is set.
+    ///
+    /// # Returns
+    /// A Result indicating success or providing an error if the save failed.
+    ///
+    /// # Errors
+    /// - `ConfigError::Io`: If there was an I/O error creating the file or directories
+    /// - `ConfigError::Serialization`: If there was an error serializing the configuration
+    fn save(&self) -> Result<(), ConfigError> {
+        telemetry::operation::<Self, _>("save", &Self::default_path(), || {
+            let content = Self::format().serialize(self)?;
+            write_with_history::<Self>(&content)
+        })
     }
 
     /// Loads the configuration from the default path.
     ///
     /// This method attempts to load and deserialize the configuration
-    /// from its default path using the specified format.
+    /// from its default path using the specified format. Types with a
+    /// [`Configuration::remote_url`] are fetched from it first, see [`remote`],
+    /// and only fall back to the local file. If that file does
+    /// not exist, a file with one of the [`FALLBACK_EXTENSIONS`] is loaded in
+    /// its own format instead, and rewritten in the declared format when
+    /// [`Configuration::migrate_on_load`] is true. If no file exists at all,
+    /// the [`template`] at [`Configuration::template_path`] is rendered.
+    /// Environment overrides are applied last when [`Configuration::env_prefix`]
+    /// is set, and never saved.
     ///
     /// # Returns
     /// A Result containing the loaded configuration or an error if loading failed.
@@ -282,40 +1281,24 @@ pub trait Configuration: Serialize + for<'de> Deserialize<'de> + Default + Sized
     /// - `ConfigError::NotFound`: If the configuration file doesn't exist
     /// - `ConfigError::Io`: If there was an I/O error reading the file
     /// - `ConfigError::Deserialization`: If there was an error deserializing the configuration
+    /// - `ConfigError::Validation`: If the configuration violates its validation rules
+    /// - `ConfigError::IntegrityMismatch`: If the file was edited outside of `save()`
+    /// - `ConfigError::Template`: If the template can't be rendered
+    /// - `ConfigError::Remote`: If the remote configuration can't be fetched and there is no cached copy or local file
     fn load() -> Result<Self, ConfigError> {
-        let path = Self::default_path();
-
-        if !path.exists() {
-            return Err(ConfigError::NotFound(path));
-        }
-
-        let mut file = File::open(&path).map_err(ConfigError::Io)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content).map_err(ConfigError::Io)?;
-
-        match Self::format() {
-            #[cfg(feature = "json")]
-            ConfigFormat::Json => serde_json::from_str(&content)
-                .map_err(|e| ConfigError::Deserialization(e.to_string())),
-            #[cfg(feature = "toml")]
-            ConfigFormat::Toml => {
-                toml::from_str(&content).map_err(|e| ConfigError::Deserialization(e.to_string()))
-            }
-            #[cfg(feature = "yaml")]
-            ConfigFormat::Yaml => serde_yaml::from_str(&content)
-                .map_err(|e| ConfigError::Deserialization(e.to_string())),
-            #[allow(unreachable_patterns)]
-            _ => Err(ConfigError::Deserialization(
-                "Selected format is not enabled".into(),
-            )),
-        }
+        telemetry::operation::<Self, _>("load", &Self::default_path(), || {
+            with_env_overrides(load_saved::<Self>()?)
+        })
     }
 
     /// Loads the configuration from the default path, or creates and saves the default if loading fails.
     ///
     /// This method attempts to load an existing configuration. If that fails because
     /// the file doesn't exist, it creates a default configuration and saves it.
-    /// If loading fails for any other reason, it logs a warning and returns the default.
+    /// The embedded [`Configuration::default_file`] is preferred over [`Default`]
+    /// and written as is when it has the declared format, keeping its comments.
+    /// Environment overrides are applied to the returned default but not saved.
+    /// If loading fails for any other reason, it emits a `tracing` warning and returns the default.
     ///
     /// # Returns
     /// Either the loaded configuration or a default configuration.
@@ -323,6 +1306,30 @@ pub trait Configuration: Serialize + for<'de> Deserialize<'de> + Default + Sized
         match Self::load() {
             Ok(config) => config,
             Err(ConfigError::NotFound(_)) => {
+                match Self::embedded_default() {
+                    Ok(Some(config)) => {
+                        let written = match Self::default_file() {
+                            Some((format, content)) if format == Self::format() => {
+                                write_with_history::<Self>(content)
+                            }
+                            _ => config.save(),
+                        };
+                        if let Err(e) = written {
+                            tracing::warn!(
+                                config = %Self::config_name(),
+                                error = telemetry::redacted_error::<Self>(&e),
+                                "failed to save the default config"
+                            );
+                        }
+                        return env_overrides_or_default(config);
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!(
+                        config = %Self::config_name(),
+                        error = telemetry::redacted_error::<Self>(&e),
+                        "failed to parse the embedded default config"
+                    ),
+                }
                 let default_config = Self::default();
                 // Create parent directories if they don't exist
                 if let Some(parent) = Self::default_path().parent() {
@@ -330,14 +1337,135 @@ pub trait Configuration: Serialize + for<'de> Deserialize<'de> + Default + Sized
                 }
                 // Save the default configuration
                 if let Err(e) = default_config.save() {
-                    eprintln!("Warning: Failed to save default config: {}", e);
+                    tracing::warn!(
+                        config = %Self::config_name(),
+                        error = telemetry::redacted_error::<Self>(&e),
+                        "failed to save the default config"
+                    );
                 }
-                default_config
+                env_overrides_or_default(default_config)
             }
             Err(e) => {
-                eprintln!("Warning: Failed to load config: {}", e);
+                tracing::warn!(
+                    config = %Self::config_name(),
+                    error = telemetry::redacted_error::<Self>(&e),
+                    "failed to load the config, using the default"
+                );
                 Self::default()
             }
         }
     }
+
+    /// Gets the directory the instances of this configuration are stored in.
+    ///
+    /// # Returns
+    /// A PathBuf pointing to `<config name>` in the configs directory.
+    fn instances_dir() -> PathBuf {
+        get_configs_dir().join(Self::config_name())
+    }
+
+    /// Gets the path of the instance `id`.
+    ///
+    /// # Returns
+    /// A PathBuf pointing to `<config name>/<id>.<extension>` in the configs directory.
+    fn instance_path(id: &str) -> PathBuf {
+        Self::instances_dir().join(format!("{id}.{}", Self::format().extension()))
+    }
+
+    /// Lists the saved instances of this configuration, sorted by id.
+    ///
+    /// # Returns
+    /// The ids of the files in [`Configuration::instances_dir`] with the
+    /// extension of the declared format, or an empty list if there are none.
+    ///
+    /// # Errors
+    /// - `ConfigError::InvalidArgument`: If the type is not declared with #[config(multi)]
+    /// - `ConfigError::Io`: If there was an I/O error reading the directory
+    fn list_instances() -> Result<Vec<String>, ConfigError> {
+        check_multi::<Self>()?;
+        let dir = Self::instances_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let extension = Self::format().extension();
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(ConfigError::Io)? {
+            let path = entry.map_err(ConfigError::Io)?.path();
+            if !path.is_file() || path.extension().is_none_or(|found| found != extension) {
+                continue;
+            }
+            if let Some(id) = path.file_stem().map(|id| id.to_string_lossy().to_string())
+                && !id.starts_with('.')
+            {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Loads the instance `id` of this configuration.
+    ///
+    /// The file is checked, decrypted and validated like in `load()`, and
+    /// the environment overrides apply to every instance.
+    ///
+    /// # Errors
+    /// - `ConfigError::InvalidArgument`: If the type is not declared with #[config(multi)] or the id is invalid
+    /// - `ConfigError::NotFound`: If the instance doesn't exist
+    /// - Any other error of [`Configuration::load`]
+    fn load_instance(id: &str) -> Result<Self, ConfigError> {
+        telemetry::operation::<Self, _>("load_instance", &Self::instance_path(id), || {
+            check_instance::<Self>(id)?;
+            let path = Self::instance_path(id);
+            if !path.exists() {
+                return Err(ConfigError::NotFound(path));
+            }
+            if Self::integrity() {
+                integrity::verify(&path, Self::integrity_key()?.as_deref())?;
+            }
+            let config: Self = Self::format().deserialize(&read_config::<Self>(&path)?)?;
+            config.validate()?;
+            with_env_overrides(config)
+        })
+    }
+
+    /// Saves this configuration as the instance `id`, creating or replacing it.
+    ///
+    /// The previous file is backed up to `<id>` in [`Configuration::history_dir`]
+    /// when [`Configuration::history_limit`] is set.
+    ///
+    /// # Errors
+    /// - `ConfigError::InvalidArgument`: If the type is not declared with #[config(multi)] or the id is invalid
+    /// - Any error of [`Configuration::save`]
+    fn save_instance(&self, id: &str) -> Result<(), ConfigError> {
+        telemetry::operation::<Self, _>("save_instance", &Self::instance_path(id), || {
+            check_instance::<Self>(id)?;
+            let content = Self::format().serialize(self)?;
+            let path = Self::instance_path(id);
+            fs::create_dir_all(Self::instances_dir()).map_err(ConfigError::Io)?;
+            if Self::history_limit() > 0 && path.exists() && read_config::<Self>(&path)? != content
+            {
+                let dir = Self::history_dir().join(id);
+                history::archive(&path, Self::format(), &dir, Self::history_limit())?;
+            }
+            write_config_file::<Self>(&path, &content)
+        })
+    }
+
+    /// Removes the instance `id` along with its checksum sidecar.
+    ///
+    /// # Errors
+    /// - `ConfigError::InvalidArgument`: If the type is not declared with #[config(multi)] or the id is invalid
+    /// - `ConfigError::NotFound`: If the instance doesn't exist
+    /// - `ConfigError::Io`: If there was an I/O error removing the file
+    fn remove_instance(id: &str) -> Result<(), ConfigError> {
+        telemetry::operation::<Self, _>("remove_instance", &Self::instance_path(id), || {
+            check_instance::<Self>(id)?;
+            let path = Self::instance_path(id);
+            if !path.exists() {
+                return Err(ConfigError::NotFound(path));
+            }
+            remove_config_file(&path)
+        })
+    }
 }
@@ -0,0 +1,408 @@
+//! Checks of every configuration file in a directory.
+//!
+//! [`lint_dir`] matches each file to the registered [`Configuration`](crate::Configuration)
+//! types stored in it and reports, without running the owning binary:
+//! - files that can't be read or parsed, or break the validation rules
+//! - keys the type doesn't know, which are silently ignored when loading
+//! - stale files missing values the current type writes, which `load()`
+//!   fills with defaults
+//! - keys that were probably renamed: when a table has a single unknown key
+//!   and a single missing one holding the same kind of value, they are
+//!   reported together as one stale key. Other renames, like several keys of
+//!   a table at once, are reported as unknown and missing keys
+//! - orphan files no registered type owns
+//!
+//! Files shared by types declared with `section` are checked table by table,
+//! reporting missing tables and tables no type owns. Instances of
+//! `#[config(multi)]` types, in a directory named after the type, are checked
+//! like the other files; other directories are skipped.
+//!
+//! Only types linked into the running binary are registered, so a lint tool
+//! should link every crate whose configurations it checks. Backups, checksum
+//! sidecars and hidden files are skipped; templates are only checked for an
+//! owner.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    ConfigError, ConfigFormat, RegisteredConfig, find_registered_config, registered_configs,
+    template,
+};
+
+/// How serious a [`LintIssue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The file loads, but probably not as intended.
+    Warning,
+    /// Loading the file fails.
+    Error,
+}
+
+/// What a [`LintIssue`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// The file can't be read or decrypted.
+    Unreadable,
+    /// The file doesn't parse as its owning type.
+    Parse,
+    /// The file breaks the validation rules of its owning type.
+    Validation,
+    /// The file has a key the owning type ignores.
+    UnknownKey,
+    /// The file lacks a value the owning type writes, or has it under a former name.
+    Stale,
+    /// No registered type owns the file.
+    Orphan,
+}
+
+/// A problem found in a configuration file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintIssue {
+    /// The file, relative to the linted directory.
+    pub path: PathBuf,
+    /// The name of the owning configuration, if there is one.
+    pub config: Option<String>,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// What the problem is about.
+    pub kind: LintKind,
+    /// The dotted path of the offending value, for unknown keys and stale files.
+    pub key: Option<String>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// Result of [`lint_dir`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LintReport {
+    /// The checked files, relative to the linted directory.
+    pub files: Vec<PathBuf>,
+    /// The problems found, in file order.
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// Checks whether any file fails to load.
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+
+    /// Renders the report as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the report can't be serialized
+    pub fn to_json(&self) -> Result<String, ConfigError> {
+        serde_json::to_string_pretty(self).map_err(|e| ConfigError::Serialization(e.to_string()))
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            writeln!(f, "{}: {severity}: {}", issue.path.display(), issue.message)?;
+        }
+        let errors = self
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        write!(
+            f,
+            "{} files checked, {errors} errors, {} warnings",
+            self.files.len(),
+            self.issues.len() - errors
+        )
+    }
+}
+
+/// Lints every configuration file in `dir`, see the module docs.
+///
+/// # Returns
+/// The checked files and the problems found in them.
+///
+/// # Errors
+/// - `ConfigError::Io`: If the directory can't be listed
+pub fn lint_dir(dir: &Path) -> Result<LintReport, ConfigError> {
+    let mut files = Vec::new();
+    for path in list_files(dir)? {
+        files.push((path, None));
+    }
+    // Instances of `#[config(multi)]` types are in a directory named after them
+    for config in crate::registered_configs() {
+        let instances = dir.join(config.config_name());
+        if config.multi() && instances.is_dir() {
+            for path in list_files(&instances)? {
+                files.push((path, Some(config.config_name())));
+            }
+        }
+    }
+    files.sort();
+
+    let mut report = LintReport::default();
+    for (path, instance_of) in files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+        lint_file(&path, &relative, instance_of.as_deref(), &mut report.issues);
+        report.files.push(relative);
+    }
+    Ok(report)
+}
+
+/// Lists the configuration files directly in `dir`.
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    Ok(fs::read_dir(dir)
+        .map_err(ConfigError::Io)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !is_skipped(path))
+        .collect())
+}
+
+/// Checks whether a file is not a configuration file.
+fn is_skipped(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    name.starts_with('.') || name.ends_with(".sum") || name.ends_with(".tmp")
+}
+
+/// Lints one file, owned by the configuration named like the file or, for
+/// instances, by `instance_of`.
+fn lint_file(path: &Path, relative: &Path, instance_of: Option<&str>, issues: &mut Vec<LintIssue>) {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let is_template = name.ends_with(&format!(".{}", template::EXTENSION));
+    let name = name
+        .strip_suffix(&format!(".{}", template::EXTENSION))
+        .unwrap_or(&name);
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let stem = instance_of.unwrap_or(stem);
+
+    let mut issue = |config: Option<&str>, severity, kind, key: Option<String>, message: String| {
+        issues.push(LintIssue {
+            path: relative.to_path_buf(),
+            config: config.map(str::to_string),
+            severity,
+            kind,
+            key,
+            message,
+        })
+    };
+
+    // Types declared with `section` share a file, each owning one table
+    let owners: Vec<&RegisteredConfig> = match instance_of {
+        Some(name) => find_registered_config(name).into_iter().collect(),
+        None => registered_configs()
+            .into_iter()
+            .filter(|config| config.file_name() == stem)
+            .collect(),
+    };
+    if owners.is_empty() {
+        issue(
+            None,
+            Severity::Warning,
+            LintKind::Orphan,
+            None,
+            format!("no registered configuration is stored in `{stem}`"),
+        );
+        return;
+    }
+    if is_template {
+        return;
+    }
+    let first_owner = owners[0].config_name();
+    let first_owner = Some(first_owner.as_str());
+    let format: ConfigFormat = match extension.parse() {
+        Ok(format) => format,
+        Err(_) => {
+            let message = format!("`.{extension}` is not a configuration format");
+            issue(first_owner, Severity::Error, LintKind::Parse, None, message);
+            return;
+        }
+    };
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            let message = format!("can't be read: {e}");
+            issue(
+                first_owner,
+                Severity::Error,
+                LintKind::Unreadable,
+                None,
+                message,
+            );
+            return;
+        }
+    };
+    // Encrypted files can be parsed by their owner, but their keys can't be compared
+    let written = format.deserialize::<Value>(&content).ok();
+
+    let mut sections = Vec::new();
+    for config in &owners {
+        let name = config.config_name();
+        let owner = Some(name.as_str());
+        let section = config.section();
+        let written = match (&section, &written) {
+            (Some(section), Some(written)) => {
+                sections.push(section.clone());
+                let Some(table) = written.get(section) else {
+                    let message = format!(
+                        "missing table `{section}`, `{name}` uses its defaults until it is saved"
+                    );
+                    let key = Some(section.clone());
+                    issue(owner, Severity::Warning, LintKind::Stale, key, message);
+                    continue;
+                };
+                Some(table)
+            }
+            _ => written.as_ref(),
+        };
+
+        let parsed = match config.parse(&content, format) {
+            Ok(parsed) => parsed,
+            Err(ConfigError::Validation(message)) => {
+                issue(owner, Severity::Error, LintKind::Validation, None, message);
+                continue;
+            }
+            Err(ConfigError::Encryption(message)) => {
+                issue(owner, Severity::Error, LintKind::Unreadable, None, message);
+                continue;
+            }
+            Err(e) => {
+                issue(owner, Severity::Error, LintKind::Parse, None, e.to_string());
+                continue;
+            }
+        };
+        let Some(written) = written else {
+            continue;
+        };
+
+        let prefix = section
+            .map(|section| format!("{section}."))
+            .unwrap_or_default();
+        let mut unknown = Vec::new();
+        let mut missing = Vec::new();
+        let mut renamed = Vec::new();
+        compare_keys(
+            &prefix,
+            written,
+            &parsed,
+            &mut unknown,
+            &mut missing,
+            &mut renamed,
+        );
+        for key in unknown {
+            let message = format!("unknown key `{key}` is ignored by `{name}`");
+            issue(
+                owner,
+                Severity::Warning,
+                LintKind::UnknownKey,
+                Some(key),
+                message,
+            );
+        }
+        for key in missing {
+            let message = format!("missing `{key}`, the default is used until the file is saved");
+            issue(
+                owner,
+                Severity::Warning,
+                LintKind::Stale,
+                Some(key),
+                message,
+            );
+        }
+        for (old, new) in renamed {
+            let message = format!(
+                "`{old}` looks like the former name of `{new}`, its value is ignored and the default is used"
+            );
+            issue(
+                owner,
+                Severity::Warning,
+                LintKind::Stale,
+                Some(new),
+                message,
+            );
+        }
+    }
+
+    // Tables of a shared file no type owns
+    if let (Some(Value::Object(tables)), true) = (&written, sections.len() == owners.len()) {
+        for key in tables.keys().filter(|key| !sections.contains(key)) {
+            let message = format!("unknown table `{key}` is not used by any configuration");
+            issue(
+                None,
+                Severity::Warning,
+                LintKind::UnknownKey,
+                Some(key.clone()),
+                message,
+            );
+        }
+    }
+}
+
+/// Lists keys only in the file (`unknown`) or only in the parsed configuration
+/// (`missing`), treating `null` as absent. A table with a single key of each
+/// holding the same kind of value has them as an `(unknown, missing)` pair in
+/// `renamed` instead.
+fn compare_keys(
+    prefix: &str,
+    written: &Value,
+    parsed: &Value,
+    unknown: &mut Vec<String>,
+    missing: &mut Vec<String>,
+    renamed: &mut Vec<(String, String)>,
+) {
+    let (Value::Object(written), Value::Object(parsed)) = (written, parsed) else {
+        return;
+    };
+    let mut unknown_here = Vec::new();
+    for (key, value) in written {
+        let path = format!("{prefix}{key}");
+        match parsed.get(key).filter(|parsed| !parsed.is_null()) {
+            Some(parsed) => {
+                compare_keys(
+                    &format!("{path}."),
+                    value,
+                    parsed,
+                    unknown,
+                    missing,
+                    renamed,
+                );
+            }
+            None if !value.is_null() => unknown_here.push((path, value)),
+            None => {}
+        }
+    }
+    let missing_here: Vec<_> = parsed
+        .iter()
+        .filter(|(key, value)| !value.is_null() && written.get(*key).is_none_or(Value::is_null))
+        .map(|(key, value)| (format!("{prefix}{key}"), value))
+        .collect();
+
+    // A single unknown key next to a single missing one of the same kind was
+    // most likely renamed
+    if let ([(old, value)], [(new, default)]) = (unknown_here.as_slice(), missing_here.as_slice())
+        && std::mem::discriminant(*value) == std::mem::discriminant(*default)
+    {
+        renamed.push((old.clone(), new.clone()));
+        return;
+    }
+    unknown.extend(unknown_here.into_iter().map(|(path, _)| path));
+    missing.extend(missing_here.into_iter().map(|(path, _)| path));
+}
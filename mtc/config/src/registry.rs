@@ -0,0 +1,237 @@
+//! Registry of every [`Configuration`] type linked into the binary.
+//!
+//! `#[derive(Configuration)]` submits a [`RegisteredConfig`] for each
+//! non-generic type, so tools can list, validate or reset all configurations
+//! without knowing their Rust types.
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::{ConfigError, ConfigFormat, ConfigSchema, Configuration};
+
+/// Type-erased description of a [`Configuration`] type.
+#[derive(Debug, Clone, Copy)]
+pub struct RegisteredConfig {
+    /// The Rust type name, e.g. `my_crate::AppConfig`.
+    pub type_name: &'static str,
+    config_name: fn() -> String,
+    format: fn() -> ConfigFormat,
+    default_path: fn() -> PathBuf,
+    env_prefix: fn() -> Option<String>,
+    file_name: fn() -> String,
+    section: fn() -> Option<String>,
+    multi: fn() -> bool,
+    history_dir: fn() -> PathBuf,
+    history_limit: fn() -> usize,
+    list_instances: fn() -> Result<Vec<String>, ConfigError>,
+    schema: fn() -> ConfigSchema,
+    defaults: fn() -> Result<Value, ConfigError>,
+    load: fn() -> Result<Value, ConfigError>,
+    parse: fn(&str, ConfigFormat) -> Result<Value, ConfigError>,
+    validate: fn() -> Result<(), ConfigError>,
+    reset: fn() -> Result<(), ConfigError>,
+    edit: fn() -> Result<bool, ConfigError>,
+}
+
+inventory::collect!(RegisteredConfig);
+
+impl RegisteredConfig {
+    /// Describes the configuration type `T`; used by the derive macro.
+    pub const fn of<T: Configuration>(type_name: &'static str) -> Self {
+        Self {
+            type_name,
+            config_name: T::config_name,
+            format: T::format,
+            default_path: T::default_path,
+            env_prefix: T::env_prefix,
+            file_name: T::file_name,
+            section: T::section,
+            multi: T::multi,
+            history_dir: T::history_dir,
+            history_limit: T::history_limit,
+            list_instances: T::list_instances,
+            schema: T::schema,
+            defaults: defaults::<T>,
+            load: load::<T>,
+            parse: parse::<T>,
+            validate: validate::<T>,
+            reset: reset::<T>,
+            edit: edit::<T>,
+        }
+    }
+
+    /// Gets the configuration name, see [`Configuration::config_name`].
+    pub fn config_name(&self) -> String {
+        (self.config_name)()
+    }
+
+    /// Gets the declared format, see [`Configuration::format`].
+    pub fn format(&self) -> ConfigFormat {
+        (self.format)()
+    }
+
+    /// Gets the default path, see [`Configuration::default_path`].
+    pub fn default_path(&self) -> PathBuf {
+        (self.default_path)()
+    }
+
+    /// Gets the environment variable prefix, see [`Configuration::env_prefix`].
+    pub fn env_prefix(&self) -> Option<String> {
+        (self.env_prefix)()
+    }
+
+    /// Gets the name of the file, see [`Configuration::file_name`].
+    pub fn file_name(&self) -> String {
+        (self.file_name)()
+    }
+
+    /// Gets the table in a shared file, see [`Configuration::section`].
+    pub fn section(&self) -> Option<String> {
+        (self.section)()
+    }
+
+    /// Whether the configuration has named instances, see [`Configuration::multi`].
+    pub fn multi(&self) -> bool {
+        (self.multi)()
+    }
+
+    /// Gets the directory of the backups, see [`Configuration::history_dir`].
+    pub fn history_dir(&self) -> PathBuf {
+        (self.history_dir)()
+    }
+
+    /// Gets the number of backups kept, see [`Configuration::history_limit`].
+    pub fn history_limit(&self) -> usize {
+        (self.history_limit)()
+    }
+
+    /// Lists the saved instances, see [`Configuration::list_instances`].
+    ///
+    /// # Errors
+    /// Same as [`Configuration::list_instances`].
+    pub fn list_instances(&self) -> Result<Vec<String>, ConfigError> {
+        (self.list_instances)()
+    }
+
+    /// Describes the fields, see [`Configuration::schema`].
+    pub fn schema(&self) -> ConfigSchema {
+        (self.schema)()
+    }
+
+    /// Gets the default configuration as a value tree.
+    ///
+    /// # Errors
+    /// - `ConfigError::Serialization`: If the default can't be serialized
+    pub fn defaults(&self) -> Result<Value, ConfigError> {
+        (self.defaults)()
+    }
+
+    /// Loads the configuration file and returns it as a value tree.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::load`], plus `ConfigError::Serialization` if
+    /// the loaded configuration can't be converted into a value tree.
+    pub fn load(&self) -> Result<Value, ConfigError> {
+        (self.load)()
+    }
+
+    /// Parses file content as this configuration, decrypting it if needed,
+    /// and checks its validation rules. Only the table of the configuration
+    /// is parsed in a shared file.
+    ///
+    /// # Returns
+    /// The parsed configuration as a value tree, as `save()` would write it.
+    ///
+    /// # Errors
+    /// - `ConfigError::Deserialization`: If the content doesn't deserialize into the type
+    /// - `ConfigError::Validation`: If the content violates the validation rules
+    /// - `ConfigError::Encryption`: If the content is encrypted and can't be decrypted
+    /// - `ConfigError::NotFound`: If the content is a shared file without the table of the configuration
+    pub fn parse(&self, content: &str, format: ConfigFormat) -> Result<Value, ConfigError> {
+        (self.parse)(content, format)
+    }
+
+    /// Checks that the configuration file exists, deserializes into its type
+    /// and passes its validation rules.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::load`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        (self.validate)()
+    }
+
+    /// Overwrites the configuration file with the default configuration.
+    ///
+    /// # Errors
+    /// Same as [`Configuration::save`].
+    pub fn reset(&self) -> Result<(), ConfigError> {
+        (self.reset)()
+    }
+
+    /// Edits the configuration on the terminal, see [`crate::editor`].
+    ///
+    /// # Returns
+    /// Whether the edited configuration was saved.
+    ///
+    /// # Errors
+    /// Same as [`crate::editor::edit_with`].
+    pub fn edit(&self) -> Result<bool, ConfigError> {
+        (self.edit)()
+    }
+}
+
+fn to_value<T: Configuration>(config: &T) -> Result<Value, ConfigError> {
+    serde_json::to_value(config).map_err(|e| ConfigError::Serialization(e.to_string()))
+}
+
+fn defaults<T: Configuration>() -> Result<Value, ConfigError> {
+    to_value(&T::default())
+}
+
+fn load<T: Configuration>() -> Result<Value, ConfigError> {
+    to_value(&T::load()?)
+}
+
+fn parse<T: Configuration>(content: &str, format: ConfigFormat) -> Result<Value, ConfigError> {
+    let content = if crate::encryption::is_sealed(content) {
+        crate::encryption::open(content, &T::encryption_key()?, &T::config_name())?
+    } else {
+        content.to_string()
+    };
+    let config: T = crate::deserialize_config(format, &content)?.ok_or_else(|| {
+        ConfigError::NotFound(PathBuf::from(format!(
+            "{}.{}",
+            T::file_name(),
+            format.extension()
+        )))
+    })?;
+    config.validate()?;
+    to_value(&config)
+}
+
+fn validate<T: Configuration>() -> Result<(), ConfigError> {
+    T::load().map(|_| ())
+}
+
+fn reset<T: Configuration>() -> Result<(), ConfigError> {
+    T::default().save()
+}
+
+fn edit<T: Configuration>() -> Result<bool, ConfigError> {
+    crate::editor::edit::<T>().map(|saved| saved.is_some())
+}
+
+/// Lists every registered configuration type, ordered by configuration name.
+pub fn registered_configs() -> Vec<&'static RegisteredConfig> {
+    let mut configs: Vec<_> = inventory::iter::<RegisteredConfig>.into_iter().collect();
+    configs.sort_by_cached_key(|config| config.config_name());
+    configs
+}
+
+/// Finds a registered configuration type by its configuration name.
+pub fn find_registered_config(name: &str) -> Option<&'static RegisteredConfig> {
+    inventory::iter::<RegisteredConfig>
+        .into_iter()
+        .find(|config| config.config_name() == name)
+}
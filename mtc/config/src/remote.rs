@@ -0,0 +1,247 @@
+//! Configurations fetched from an HTTP server.
+//!
+//! A type declaring `#[config(remote = "https://config.example.com/app.toml")]`
+//! is loaded from that URL, parsed in its declared format. `$VAR` and
+//! `${VAR}` in the URL are read from the environment, so each deployment can
+//! point to its own server: `remote = "${CONFIG_SERVER}/app.toml"`.
+//!
+//! Every document fetched and parsed successfully is cached in `.remote/` of
+//! the configs directory with its `ETag`. Later fetches send `If-None-Match`
+//! and reuse the cache on `304 Not Modified`. `load()` tries, in order:
+//! 1. the server
+//! 2. the cached copy, when the server can't be reached, answers with an
+//!    error or sends a document that doesn't parse
+//! 3. the local configuration file or template, as for any other type
+//!
+//! Environment overrides apply on top of whichever was used. Saving still
+//! writes the local file, the server is never written to. The cache is
+//! plaintext, so `remote` can't be combined with `encrypted`. Requires the
+//! `remote` feature.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::telemetry::redacted_url;
+use crate::{ConfigError, Configuration, get_configs_dir};
+
+/// Directory in the configs directory holding fetched documents.
+pub const CACHE_DIR: &str = ".remote";
+
+/// Time allowed for a fetch before the cached copy is used.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Checks whether the `remote` feature is enabled.
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "remote")
+}
+
+/// Where the content of a [`RemoteDocument`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteOrigin {
+    /// The server sent a new document.
+    Fetched,
+    /// The server confirmed the cached copy is current.
+    NotModified,
+    /// The server couldn't be used, this is the cached copy.
+    Cached,
+}
+
+/// A document fetched by a [`RemoteSource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDocument {
+    /// The text of the document.
+    pub content: String,
+    /// The `ETag` the server sent with it, if any.
+    pub etag: Option<String>,
+    /// Where the content comes from.
+    pub origin: RemoteOrigin,
+    /// Why the server couldn't be used, for [`RemoteOrigin::Cached`].
+    pub error: Option<String>,
+}
+
+/// A URL configurations are fetched from, with its local cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSource {
+    url: String,
+    cache_path: PathBuf,
+    timeout: Duration,
+}
+
+impl RemoteSource {
+    /// Creates a source fetching `url`, caching the document at `cache_path`.
+    ///
+    /// `$VAR` and `${VAR}` in the URL are expanded when fetching.
+    pub fn new(url: impl Into<String>, cache_path: impl Into<PathBuf>) -> Self {
+        Self {
+            url: url.into(),
+            cache_path: cache_path.into(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Creates the source of the configuration `T`, see [`Configuration::remote_url`].
+    ///
+    /// # Returns
+    /// The source, caching at `.remote/<config name>.<extension>` in the
+    /// configs directory, or None if `T` has no remote URL.
+    pub fn of<T: Configuration>() -> Option<Self> {
+        let url = T::remote_url()?;
+        let cache_path = get_configs_dir().join(CACHE_DIR).join(format!(
+            "{}.{}",
+            T::config_name(),
+            T::format().extension()
+        ));
+        Some(Self::new(url, cache_path))
+    }
+
+    /// Sets the time allowed for a fetch, [`DEFAULT_TIMEOUT`] by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Gets the URL, as given.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gets the path of the cached document.
+    pub fn cache_path(&self) -> &Path {
+        &self.cache_path
+    }
+
+    /// Gets the path of the `ETag` of the cached document.
+    fn etag_path(&self) -> PathBuf {
+        let mut path = self.cache_path.as_os_str().to_owned();
+        path.push(".etag");
+        PathBuf::from(path)
+    }
+
+    /// Reads the cached document.
+    ///
+    /// # Returns
+    /// The cached copy, with the [`RemoteOrigin::Cached`] origin, or None if
+    /// nothing was cached yet.
+    pub fn cached(&self) -> Option<RemoteDocument> {
+        let content = fs::read_to_string(&self.cache_path).ok()?;
+        let etag = fs::read_to_string(self.etag_path())
+            .ok()
+            .map(|etag| etag.trim().to_string())
+            .filter(|etag| !etag.is_empty());
+        Some(RemoteDocument {
+            content,
+            etag,
+            origin: RemoteOrigin::Cached,
+            error: None,
+        })
+    }
+
+    /// Fetches the document, falling back to the cached copy.
+    ///
+    /// The cache is not updated, call [`RemoteSource::store`] once the
+    /// document is known to be valid.
+    ///
+    /// # Errors
+    /// - `ConfigError::Remote`: If the server can't be used and nothing is cached
+    pub fn fetch(&self) -> Result<RemoteDocument, ConfigError> {
+        let cached = self.cached();
+        let etag = cached.as_ref().and_then(|cached| cached.etag.as_deref());
+        match self.request(etag) {
+            Ok(Some(document)) => Ok(document),
+            Ok(None) => match cached {
+                Some(cached) => Ok(RemoteDocument {
+                    origin: RemoteOrigin::NotModified,
+                    ..cached
+                }),
+                None => Err(ConfigError::Remote(format!(
+                    "{} answered 304 Not Modified, but nothing is cached",
+                    redacted_url(&self.url)
+                ))),
+            },
+            Err(message) => match cached {
+                Some(cached) => Ok(RemoteDocument {
+                    error: Some(message),
+                    ..cached
+                }),
+                None => Err(ConfigError::Remote(message)),
+            },
+        }
+    }
+
+    /// Caches a freshly fetched document; documents read from the cache are
+    /// left as they are.
+    ///
+    /// # Errors
+    /// - `ConfigError::Io`: If the cache can't be written
+    pub fn store(&self, document: &RemoteDocument) -> Result<(), ConfigError> {
+        if document.origin != RemoteOrigin::Fetched {
+            return Ok(());
+        }
+        if let Some(parent) = self.cache_path.parent() {
+            fs::create_dir_all(parent).map_err(ConfigError::Io)?;
+        }
+        let mut temporary = self.cache_path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, &document.content).map_err(ConfigError::Io)?;
+        fs::rename(&temporary, &self.cache_path).map_err(ConfigError::Io)?;
+        match &document.etag {
+            Some(etag) => fs::write(self.etag_path(), etag).map_err(ConfigError::Io),
+            None if self.etag_path().exists() => {
+                fs::remove_file(self.etag_path()).map_err(ConfigError::Io)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sends the request.
+    ///
+    /// # Returns
+    /// The new document, or None if the server answered `304 Not Modified`.
+    ///
+    /// # Errors
+    /// Why the server couldn't be used.
+    #[cfg(feature = "remote")]
+    fn request(&self, etag: Option<&str>) -> Result<Option<RemoteDocument>, String> {
+        let url = crate::types::expand_vars(&self.url).map_err(|reason| {
+            format!("invalid remote URL `{}`: {reason}", redacted_url(&self.url))
+        })?;
+        let shown = redacted_url(&url);
+        let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+        let mut request = agent.get(&url);
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        match request.call() {
+            Ok(response) if response.status() == 304 => Ok(None),
+            Ok(response) => {
+                let etag = response.header("ETag").map(str::to_string);
+                let content = response
+                    .into_string()
+                    .map_err(|e| format!("can't read the answer of {shown}: {e}"))?;
+                Ok(Some(RemoteDocument {
+                    content,
+                    etag,
+                    origin: RemoteOrigin::Fetched,
+                    error: None,
+                }))
+            }
+            Err(ureq::Error::Status(code, response)) => Err(format!(
+                "{shown} answered {code} {}",
+                response.status_text()
+            )),
+            Err(e) => Err(format!(
+                "can't reach {shown}: {}",
+                redacted_url(&e.to_string())
+            )),
+        }
+    }
+
+    #[cfg(not(feature = "remote"))]
+    fn request(&self, _etag: Option<&str>) -> Result<Option<RemoteDocument>, String> {
+        Err(format!(
+            "fetching {} requires the `remote` feature",
+            redacted_url(&self.url)
+        ))
+    }
+}
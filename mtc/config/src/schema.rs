@@ -0,0 +1,276 @@
+//! Runtime description of a configuration's fields.
+//!
+//! `#[derive(Configuration)]` and `#[derive(ConfigSection)]` generate a
+//! [`ConfigSchema`] listing every field with its Rust type, doc comment,
+//! default value, validation rules and secret flag. Nested sections marked
+//! with `#[config(nested)]` carry their own schema, so documentation, CLI help
+//! or editors can walk the whole tree without parsing the source.
+//!
+//! Field options are set with `#[config(...)]` on the field:
+//! - `secret`: the value is sensitive and should not be displayed
+//! - `nested`: the field type derives [`ConfigSection`]
+//! - `min = N` / `max = N`: numeric bounds, or length bounds for strings and sequences
+//! - `non_empty`: strings, sequences and tables must not be empty
+//! - `one_of = ["a", "b"]`: the value must be one of the listed strings
+//!
+//! Fields whose type implements [`ValueFormat`], like the [`types`](crate::types),
+//! also describe the text they expect.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::ConfigError;
+
+/// Implemented by `#[derive(ConfigSection)]` for types nested in a configuration.
+pub trait ConfigSection {
+    /// Describes the section.
+    ///
+    /// `defaults` is the value of this section in the default configuration
+    /// of its parent, used to render the default of each field.
+    fn section_schema(defaults: Option<&Value>) -> ConfigSchema;
+}
+
+/// Optional sections are described by their inner type.
+impl<T: ConfigSection> ConfigSection for Option<T> {
+    fn section_schema(defaults: Option<&Value>) -> ConfigSchema {
+        T::section_schema(defaults)
+    }
+}
+
+/// Description of a configuration type or nested section.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigSchema {
+    /// The Rust type name, without its module path.
+    pub type_name: &'static str,
+    /// The doc comment of the type.
+    pub doc: &'static str,
+    /// The fields, in declaration order; empty for enums and newtypes.
+    pub fields: Vec<FieldSchema>,
+}
+
+/// Description of a single field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldSchema {
+    /// The serialized name of the field.
+    pub name: &'static str,
+    /// The Rust type of the field, as written in the source.
+    pub type_name: &'static str,
+    /// The doc comment of the field.
+    pub doc: &'static str,
+    /// The default value rendered as JSON, `None` if it has none or is `null`.
+    pub default: Option<String>,
+    /// The validation rules checked when the configuration is loaded.
+    pub rules: Vec<ValidationRule>,
+    /// Whether the value is sensitive.
+    pub secret: bool,
+    /// The schema of the field type, for fields marked `nested`.
+    pub nested: Option<ConfigSchema>,
+    /// The text expected for fields of a [`ValueFormat`] type, e.g. a
+    /// `duration like 30s`; `None` for other types.
+    pub value_format: Option<&'static str>,
+}
+
+/// Implemented by value types written as formatted strings, see [`crate::types`].
+pub trait ValueFormat {
+    /// Describes the expected text after "a", e.g. for sizes ``size like `512B`, `10MiB` or `1GB` ``.
+    const FORMAT: &'static str;
+}
+
+impl<T: ValueFormat> ValueFormat for Option<T> {
+    const FORMAT: &'static str = T::FORMAT;
+}
+
+/// Gets the [`ValueFormat`] of a field type if it has one; used by the derive
+/// macro as `(&FormatOf::<T>::new()).format()`, which picks [`HasFormat`]
+/// over [`NoFormat`] when `T: ValueFormat`.
+#[doc(hidden)]
+pub struct FormatOf<T: ?Sized>(std::marker::PhantomData<T>);
+
+impl<T: ?Sized> FormatOf<T> {
+    pub const fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T: ?Sized> Default for FormatOf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub trait HasFormat {
+    fn format(&self) -> Option<&'static str>;
+}
+
+impl<T: ValueFormat + ?Sized> HasFormat for FormatOf<T> {
+    fn format(&self) -> Option<&'static str> {
+        Some(T::FORMAT)
+    }
+}
+
+#[doc(hidden)]
+pub trait NoFormat {
+    fn format(&self) -> Option<&'static str>;
+}
+
+impl<T: ?Sized> NoFormat for &FormatOf<T> {
+    fn format(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// A validation rule declared on a field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationRule {
+    /// Minimum number, or minimum length of a string, sequence or table.
+    Min(f64),
+    /// Maximum number, or maximum length of a string, sequence or table.
+    Max(f64),
+    /// Strings, sequences and tables must not be empty.
+    NonEmpty,
+    /// The value must be one of the listed strings.
+    OneOf(Vec<&'static str>),
+}
+
+impl ConfigSchema {
+    /// Creates a schema without fields.
+    pub fn new(type_name: &'static str, doc: &'static str) -> Self {
+        Self {
+            type_name,
+            doc,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Gets a field by its serialized name.
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Checks whether any field, nested ones included, declares validation rules.
+    pub fn has_rules(&self) -> bool {
+        self.fields.iter().any(|field| {
+            !field.rules.is_empty() || field.nested.as_ref().is_some_and(Self::has_rules)
+        })
+    }
+
+    /// Lists the dotted paths of every secret field, nested ones included.
+    pub fn secret_paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        for field in &self.fields {
+            if field.secret {
+                paths.push(field.name.to_string());
+            } else if let Some(nested) = &field.nested {
+                paths.extend(
+                    nested
+                        .secret_paths()
+                        .into_iter()
+                        .map(|path| format!("{}.{path}", field.name)),
+                );
+            }
+        }
+        paths
+    }
+
+    /// Checks a serialized configuration against the validation rules.
+    ///
+    /// Missing and `null` values are not checked.
+    ///
+    /// # Errors
+    /// - `ConfigError::Validation`: If a rule is violated, listing every violation
+    pub fn validate(&self, value: &Value) -> Result<(), ConfigError> {
+        let mut violations = Vec::new();
+        self.collect_violations("", value, &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(violations.join("; ")))
+        }
+    }
+
+    fn collect_violations(&self, prefix: &str, value: &Value, violations: &mut Vec<String>) {
+        for field in &self.fields {
+            let Some(value) = value.get(field.name).filter(|value| !value.is_null()) else {
+                continue;
+            };
+            let path = format!("{prefix}{}", field.name);
+            for rule in &field.rules {
+                if let Err(message) = rule.check(value) {
+                    violations.push(format!("`{path}` {message}"));
+                }
+            }
+            if let Some(nested) = &field.nested {
+                nested.collect_violations(&format!("{path}."), value, violations);
+            }
+        }
+    }
+
+    /// Serializes the default configuration; used by the derive macro.
+    #[doc(hidden)]
+    pub fn defaults_of<T: Serialize>(config: &T) -> Value {
+        serde_json::to_value(config).unwrap_or(Value::Null)
+    }
+
+    /// Gets the default of a field from its parent's defaults; used by the derive macro.
+    #[doc(hidden)]
+    pub fn default_field<'a>(defaults: Option<&'a Value>, name: &str) -> Option<&'a Value> {
+        defaults
+            .and_then(|defaults| defaults.get(name))
+            .filter(|value| !value.is_null())
+    }
+}
+
+impl ValidationRule {
+    /// Checks a single value, returning what is wrong with it.
+    ///
+    /// Rules that don't apply to the kind of value, like `min` on a boolean,
+    /// always pass.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let size = match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => Some(string.chars().count() as f64),
+            Value::Array(items) => Some(items.len() as f64),
+            Value::Object(map) => Some(map.len() as f64),
+            _ => None,
+        };
+        let unit = if value.is_number() { "" } else { " in length" };
+        match self {
+            ValidationRule::Min(min) if size.is_some_and(|size| size < *min) => {
+                Err(format!("must be at least {min}{unit}"))
+            }
+            ValidationRule::Max(max) if size.is_some_and(|size| size > *max) => {
+                Err(format!("must be at most {max}{unit}"))
+            }
+            ValidationRule::NonEmpty if size == Some(0.0) && !value.is_number() => {
+                Err("must not be empty".to_string())
+            }
+            ValidationRule::OneOf(allowed)
+                if !value.as_str().is_some_and(|value| allowed.contains(&value)) =>
+            {
+                Err(format!("must be one of: {}", allowed.join(", ")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ValidationRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationRule::Min(min) => write!(f, "min = {min}"),
+            ValidationRule::Max(max) => write!(f, "max = {max}"),
+            ValidationRule::NonEmpty => write!(f, "non_empty"),
+            ValidationRule::OneOf(allowed) => write!(f, "one_of = [{}]", allowed.join(", ")),
+        }
+    }
+}
+
+/// Checks whether a dotted path is `parent` or one of its children.
+pub(crate) fn is_within(path: &str, parent: &str) -> bool {
+    path.strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+}
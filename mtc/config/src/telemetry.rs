@@ -0,0 +1,130 @@
+//! `tracing` spans and events of configuration operations.
+//!
+//! Every operation runs in a `config` span with the `operation`, `config`
+//! name, `path` and `format` fields, and records its `duration_ms` and
+//! `outcome` (`ok` or the error kind) when it ends. Values and error messages
+//! that may quote the file are left out for types with secret fields or
+//! encryption.
+
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::Value;
+use tracing::field::Empty;
+
+use crate::schema::is_within;
+use crate::{ConfigDiff, ConfigError, Configuration};
+
+/// Text logged in place of a secret value.
+const REDACTED: &str = "*secret*";
+
+/// Runs `run` in a span describing `operation` on the configuration `T` at `path`.
+pub(crate) fn operation<T: Configuration, R>(
+    operation: &'static str,
+    path: &Path,
+    run: impl FnOnce() -> Result<R, ConfigError>,
+) -> Result<R, ConfigError> {
+    let span = tracing::debug_span!(
+        "config",
+        operation,
+        config = %T::config_name(),
+        path = %path.display(),
+        format = %T::format(),
+        duration_ms = Empty,
+        outcome = Empty,
+    );
+    let _entered = span.enter();
+    let start = Instant::now();
+    let result = run();
+    span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+    match &result {
+        Ok(_) => {
+            span.record("outcome", "ok");
+            tracing::debug!("config {operation} succeeded");
+        }
+        Err(e) => {
+            span.record("outcome", outcome(e));
+            let error = redacted_error::<T>(e);
+            // A missing file is expected, `load_or_default` creates it
+            if matches!(e, ConfigError::NotFound(_)) {
+                tracing::debug!(error, "config {operation} failed");
+            } else {
+                tracing::warn!(error, "config {operation} failed");
+            }
+        }
+    }
+    result
+}
+
+/// Names the kind of an error for the `outcome` field.
+fn outcome(error: &ConfigError) -> &'static str {
+    match error {
+        ConfigError::Io(_) => "io_error",
+        ConfigError::Serialization(_) => "serialization_error",
+        ConfigError::Deserialization(_) => "deserialization_error",
+        ConfigError::NotFound(_) => "not_found",
+        ConfigError::UnknownFormat(_) => "unknown_format",
+        ConfigError::VersionNotFound(_) => "version_not_found",
+        ConfigError::Validation(_) => "validation_error",
+        ConfigError::Encryption(_) => "encryption_error",
+        ConfigError::IntegrityMismatch(_) => "integrity_mismatch",
+        ConfigError::Template(_) => "template_error",
+        ConfigError::Remote(_) => "remote_error",
+        ConfigError::InvalidArgument(_) => "invalid_argument",
+    }
+}
+
+/// Whether values of `T` must not be logged.
+fn has_secrets<T: Configuration>() -> bool {
+    T::encrypted() || !T::schema().secret_paths().is_empty()
+}
+
+/// Renders an error, leaving out parser messages that may quote secret values.
+pub(crate) fn redacted_error<T: Configuration>(error: &ConfigError) -> String {
+    match error {
+        ConfigError::Deserialization(_) | ConfigError::Template(_) if has_secrets::<T>() => {
+            format!("{} ({REDACTED} details)", outcome(error))
+        }
+        error => error.to_string(),
+    }
+}
+
+/// Logs the values environment overrides changed in `T`, secret ones redacted.
+pub(crate) fn env_overrides<T: Configuration>(prefix: &str, before: &T, after: &T) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    let Ok(diff) = ConfigDiff::between(before, after) else {
+        return;
+    };
+    let secrets = T::schema().secret_paths();
+    let encrypted = T::encrypted();
+    for change in &diff.changes {
+        let secret = encrypted || secrets.iter().any(|secret| is_within(&change.path, secret));
+        let value = match &change.new {
+            _ if secret => REDACTED.to_string(),
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => "null".to_string(),
+        };
+        tracing::debug!(
+            config = %T::config_name(),
+            prefix,
+            key = %change.path,
+            value,
+            "environment override applied"
+        );
+    }
+}
+
+/// Hides the user name and password of a URL, e.g. for logs and error messages.
+pub(crate) fn redacted_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    match rest[..authority_end].rfind('@') {
+        Some(at) => format!("{scheme}://{REDACTED}@{}", &rest[at + 1..]),
+        None => url.to_string(),
+    }
+}
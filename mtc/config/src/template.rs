@@ -0,0 +1,188 @@
+//! Configuration files rendered from minijinja templates.
+//!
+//! A template sits next to the configuration file with `.j2` appended to its
+//! name, e.g. `server.toml.j2` for `server.toml`. When no configuration file
+//! exists, `load()` renders the template and parses the result in the declared
+//! format; the rendered text is not written unless
+//! [`Configuration::materialize`](crate::Configuration::materialize) or
+//! [`Configuration::render_to`](crate::Configuration::render_to) is called.
+//!
+//! Templates see three variables:
+//! - `env`: the environment variables of the process
+//! - `workspace`: `root` and `configs_dir` paths, plus the `[workspace]` table
+//!   of the root `Cargo.toml` (`package`, `members`, `metadata`, ...) when the
+//!   `toml` feature is enabled
+//! - `configs`: every registered configuration by name, loaded on first use,
+//!   e.g. `{{ configs.database.port }}`
+//!
+//! Undefined variables are errors rather than empty strings, use the
+//! `default` filter for optional ones: `{{ env.PORT | default(8080) }}`.
+//! Requires the `templates` feature.
+
+use std::path::{Path, PathBuf};
+
+use crate::ConfigError;
+
+/// Extension appended to a configuration file name to get its template.
+pub const EXTENSION: &str = "j2";
+
+/// Checks whether the `templates` feature is enabled.
+pub const fn is_enabled() -> bool {
+    cfg!(feature = "templates")
+}
+
+/// Gets the path of the template rendering the configuration file at `path`.
+pub fn template_path(path: &Path) -> PathBuf {
+    let mut template = path.as_os_str().to_owned();
+    template.push(".");
+    template.push(EXTENSION);
+    PathBuf::from(template)
+}
+
+/// Finds the workspace root: the closest ancestor of the current directory
+/// whose `Cargo.toml` declares a `[workspace]`, else the current directory.
+pub fn workspace_root() -> PathBuf {
+    let current = std::env::current_dir().unwrap_or_default();
+    current
+        .ancestors()
+        .find(|dir| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .is_ok_and(|manifest| manifest.lines().any(|line| line.trim() == "[workspace]"))
+        })
+        .unwrap_or(&current)
+        .to_path_buf()
+}
+
+/// Renders the template `source` of the configuration `name`.
+///
+/// # Errors
+/// - `ConfigError::Template`: If the template is invalid, uses an undefined
+///   variable, or a configuration it uses can't be loaded
+#[cfg(feature = "templates")]
+pub fn render(name: &str, source: &str) -> Result<String, ConfigError> {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use minijinja::{Environment, UndefinedBehavior, Value};
+
+    let already_rendering = RENDERING.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let cycle = stack.iter().any(|rendering| rendering == name);
+        stack.push(name.to_string());
+        cycle
+    });
+    let _guard = RenderingGuard;
+    if already_rendering {
+        return Err(ConfigError::Template(format!(
+            "the template of `{name}` uses itself through `configs`"
+        )));
+    }
+
+    let mut environment = Environment::new();
+    environment.set_undefined_behavior(UndefinedBehavior::Strict);
+    let configs = Arc::new(Configs::default());
+    let context = BTreeMap::from([
+        (
+            "env",
+            Value::from_serialize(crate::env_vars().into_iter().collect::<BTreeMap<_, _>>()),
+        ),
+        ("workspace", workspace()),
+        ("configs", Value::from_dyn_object(configs.clone())),
+    ]);
+
+    let template_name = format!("{name}.{EXTENSION}");
+    environment
+        .render_named_str(&template_name, source, context)
+        .map_err(|e| {
+            let mut message = e.to_string();
+            for error in configs.errors.lock().unwrap().iter() {
+                message.push_str(&format!(", {error}"));
+            }
+            ConfigError::Template(message)
+        })
+}
+
+#[cfg(not(feature = "templates"))]
+pub fn render(name: &str, _source: &str) -> Result<String, ConfigError> {
+    Err(ConfigError::Template(format!(
+        "rendering the template of `{name}` requires the `templates` feature"
+    )))
+}
+
+#[cfg(feature = "templates")]
+thread_local! {
+    /// Names of the configurations whose templates are being rendered.
+    static RENDERING: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Pops the configuration from [`RENDERING`] when its rendering ends.
+#[cfg(feature = "templates")]
+struct RenderingGuard;
+
+#[cfg(feature = "templates")]
+impl Drop for RenderingGuard {
+    fn drop(&mut self) {
+        RENDERING.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// The `workspace` variable.
+#[cfg(feature = "templates")]
+fn workspace() -> minijinja::Value {
+    let root = workspace_root();
+    let mut workspace = serde_json::Map::new();
+    #[cfg(feature = "toml")]
+    if let Some(serde_json::Value::Object(table)) = std::fs::read_to_string(root.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| toml::from_str::<serde_json::Value>(&manifest).ok())
+        .and_then(|manifest| manifest.get("workspace").cloned())
+    {
+        workspace = table;
+    }
+    workspace.insert("root".to_string(), root.to_string_lossy().into());
+    workspace.insert(
+        "configs_dir".to_string(),
+        crate::get_configs_dir().to_string_lossy().into(),
+    );
+    minijinja::Value::from_serialize(workspace)
+}
+
+/// The `configs` variable, loading registered configurations on first use.
+#[cfg(feature = "templates")]
+#[derive(Debug, Default)]
+struct Configs {
+    /// Why configurations used by the template couldn't be loaded.
+    errors: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(feature = "templates")]
+impl minijinja::value::Object for Configs {
+    fn get_value(self: &std::sync::Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
+        let name = key.as_str()?;
+        let Some(config) = crate::find_registered_config(name) else {
+            self.errors
+                .lock()
+                .unwrap()
+                .push(format!("no configuration named `{name}`"));
+            return None;
+        };
+        match config.load() {
+            Ok(value) => Some(minijinja::Value::from_serialize(value)),
+            Err(e) => {
+                self.errors
+                    .lock()
+                    .unwrap()
+                    .push(format!("can't load `{name}`: {e}"));
+                None
+            }
+        }
+    }
+
+    fn enumerate(self: &std::sync::Arc<Self>) -> minijinja::value::Enumerator {
+        let names: Vec<minijinja::Value> = crate::registered_configs()
+            .into_iter()
+            .map(|config| config.config_name().into())
+            .collect();
+        minijinja::value::Enumerator::Values(names)
+    }
+}
@@ -0,0 +1,218 @@
+//! Helpers for testing code that uses [`Configuration`] types.
+//!
+//! [`TestConfigRoot`] points [`get_configs_dir`](crate::get_configs_dir) at a
+//! fresh temporary directory for the current thread until it is dropped, so
+//! tests running in parallel don't share configuration files and don't need
+//! unique configuration names. Requires the `testing` feature.
+//!
+//! ```rust,ignore
+//! let root = TestConfigRoot::new();
+//! root.seed::<AppConfig>("debug = true\n");
+//! let mut config = AppConfig::load().unwrap();
+//! config.debug = false;
+//! config.save().unwrap();
+//! root.assert_saved(&config);
+//! ```
+//!
+//! [`TestConfigRoot::set_env`] and [`TestConfigRoot::remove_env`] set and
+//! hide environment variables the same way.
+//!
+//! The override is per thread: threads spawned by the test and
+//! process-wide instances from `#[config(global)]` still use the regular
+//! configs directory.
+//!
+//! [`check_round_trip`] and [`assert_round_trip`] generate random values of a
+//! type implementing [`arbitrary::Arbitrary`] and check that each one is
+//! read back unchanged in every enabled format, explaining failures like
+//! TOML's lack of null or map keys that aren't strings.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use tempdir::TempDir;
+
+use crate::{CONFIG_ROOT_OVERRIDE, Configuration, ENV_OVERRIDE};
+
+mod round_trip;
+
+pub use arbitrary;
+pub use round_trip::{
+    RoundTripFailure, RoundTripProblem, RoundTripReport, SEED_ENV, assert_round_trip,
+    check_round_trip,
+};
+
+/// An isolated configs directory for the current thread, removed on drop.
+pub struct TestConfigRoot {
+    dir: TempDir,
+    previous: Option<PathBuf>,
+    previous_env: BTreeMap<String, Option<String>>,
+    // The override is thread-local, so the guard must be dropped on its thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Default for TestConfigRoot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestConfigRoot {
+    /// Creates a temporary configs directory and makes it the configs
+    /// directory of the current thread.
+    ///
+    /// # Panics
+    /// If the temporary directory can't be created.
+    pub fn new() -> Self {
+        let dir = TempDir::new("mtc-config").expect("failed to create a temporary configs dir");
+        let previous =
+            CONFIG_ROOT_OVERRIDE.with(|root| root.replace(Some(dir.path().to_path_buf())));
+        let previous_env = ENV_OVERRIDE.with(|vars| vars.borrow().clone());
+        Self {
+            dir,
+            previous,
+            previous_env,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Sets an environment variable for the current thread until the root is
+    /// dropped, e.g. an override read by `load()` or a `$VAR` in a path or
+    /// remote URL. The process environment, shared by tests running in
+    /// parallel, is left untouched.
+    pub fn set_env(&self, name: &str, value: &str) -> &Self {
+        ENV_OVERRIDE.with(|vars| {
+            vars.borrow_mut()
+                .insert(name.to_string(), Some(value.to_string()))
+        });
+        self
+    }
+
+    /// Hides an environment variable from the current thread until the root
+    /// is dropped, e.g. a key exported in the shell running the tests.
+    pub fn remove_env(&self, name: &str) -> &Self {
+        ENV_OVERRIDE.with(|vars| vars.borrow_mut().insert(name.to_string(), None));
+        self
+    }
+
+    /// Gets the temporary configs directory.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes `content` as is to the default path of `T`.
+    ///
+    /// # Panics
+    /// If the file can't be written.
+    pub fn seed<T: Configuration>(&self, content: &str) -> &Self {
+        self.write(&T::default_path(), content)
+    }
+
+    /// Writes `content` to a path relative to the configs directory, e.g. a
+    /// file in another format or a backup in `.history`.
+    ///
+    /// # Panics
+    /// If the file can't be written.
+    pub fn seed_file(&self, relative: impl AsRef<Path>, content: &str) -> &Self {
+        self.write(&self.path().join(relative), content)
+    }
+
+    /// Saves `config` with [`Configuration::save`].
+    ///
+    /// # Panics
+    /// If saving fails.
+    pub fn seed_config<T: Configuration>(&self, config: &T) -> &Self {
+        config
+            .save()
+            .expect("failed to save the seeded configuration");
+        self
+    }
+
+    fn write(&self, path: &Path, content: &str) -> &Self {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create the fixture directory");
+        }
+        fs::write(path, content).expect("failed to write the fixture");
+        self
+    }
+
+    /// Reads the saved file of `T`.
+    ///
+    /// # Panics
+    /// If no file was saved.
+    pub fn saved_content<T: Configuration>(&self) -> String {
+        let path = T::default_path();
+        fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("no saved configuration at {}: {e}", path.display()))
+    }
+
+    /// Lists the files in the configs directory, relative to it and sorted.
+    pub fn files(&self) -> Vec<PathBuf> {
+        fn walk(dir: &Path, root: &Path, files: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    walk(&path, root, files);
+                } else if let Ok(relative) = path.strip_prefix(root) {
+                    files.push(relative.to_path_buf());
+                }
+            }
+        }
+        let mut files = Vec::new();
+        walk(self.path(), self.path(), &mut files);
+        files.sort();
+        files
+    }
+
+    /// Asserts that loading `T` gives `expected`.
+    ///
+    /// # Panics
+    /// If loading fails or the loaded configuration differs, showing the
+    /// structural diff.
+    pub fn assert_saved<T: Configuration + PartialEq + Debug>(&self, expected: &T) {
+        let saved =
+            T::load().unwrap_or_else(|e| panic!("failed to load {}: {e}", T::config_name()));
+        if &saved != expected {
+            let diff = saved
+                .diff(expected)
+                .map(|diff| diff.to_text())
+                .unwrap_or_default();
+            panic!(
+                "saved {} differs from the expected configuration:\n{diff}",
+                T::config_name()
+            );
+        }
+    }
+
+    /// Asserts that the saved file of `T` contains `needle`.
+    ///
+    /// # Panics
+    /// If no file was saved or it doesn't contain `needle`.
+    pub fn assert_saved_contains<T: Configuration>(&self, needle: &str) {
+        let content = self.saved_content::<T>();
+        assert!(
+            content.contains(needle),
+            "saved {} does not contain {needle:?}:\n{content}",
+            T::config_name()
+        );
+    }
+
+    /// Asserts that no file of `T` exists, in any format.
+    ///
+    /// # Panics
+    /// If a file exists.
+    pub fn assert_not_saved<T: Configuration>(&self) {
+        if let Some((path, _)) = T::find_existing() {
+            panic!("{} was saved at {}", T::config_name(), path.display());
+        }
+    }
+}
+
+impl Drop for TestConfigRoot {
+    fn drop(&mut self) {
+        CONFIG_ROOT_OVERRIDE.with(|root| root.replace(self.previous.take()));
+        ENV_OVERRIDE.with(|vars| vars.replace(std::mem::take(&mut self.previous_env)));
+    }
+}
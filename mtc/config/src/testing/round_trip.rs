@@ -0,0 +1,257 @@
+//! Randomized `save -> load` round trips of a configuration type through
+//! every enabled or registered format.
+
+use std::fmt::{self, Debug};
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use super::TestConfigRoot;
+use crate::{ConfigDiff, ConfigFormat, Configuration, FormatRegistry};
+
+/// Environment variable fixing the seed of [`check_round_trip`], to replay a
+/// reported failure.
+pub const SEED_ENV: &str = "MTC_CONFIG_ROUND_TRIP_SEED";
+
+/// Why a value didn't survive a round trip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundTripProblem {
+    /// The value could not be written in the format, or saved to its file.
+    Serialize(String),
+    /// The written value could not be read back.
+    Deserialize(String),
+    /// The value read back differs from the one written.
+    Mismatch(String),
+}
+
+/// The first failing value found for a format.
+#[derive(Debug, Clone)]
+pub struct RoundTripFailure {
+    /// The format the value failed in.
+    pub format: ConfigFormat,
+    /// The failing value, `Debug` formatted.
+    pub value: String,
+    /// The text written, if serialization succeeded.
+    pub written: Option<String>,
+    /// What went wrong.
+    pub problem: RoundTripProblem,
+    /// What usually causes this kind of failure, if known.
+    pub hint: Option<&'static str>,
+}
+
+/// Result of [`check_round_trip`], with at most one failure per format.
+#[derive(Debug, Clone)]
+pub struct RoundTripReport {
+    /// The seed the values were generated from, see [`SEED_ENV`].
+    pub seed: u64,
+    /// The number of values generated.
+    pub iterations: usize,
+    /// The formats that were checked.
+    pub formats: Vec<ConfigFormat>,
+    /// The first failure found in each failing format.
+    pub failures: Vec<RoundTripFailure>,
+}
+
+impl RoundTripReport {
+    /// Checks whether every value survived every format.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Gets the failure found for `format`, if any.
+    pub fn failure(&self, format: ConfigFormat) -> Option<&RoundTripFailure> {
+        self.failures
+            .iter()
+            .find(|failure| failure.format == format)
+    }
+
+    /// Lists the checked formats every value survived.
+    pub fn supported_formats(&self) -> Vec<ConfigFormat> {
+        self.formats
+            .iter()
+            .copied()
+            .filter(|format| self.failure(*format).is_none())
+            .collect()
+    }
+}
+
+impl fmt::Display for RoundTripReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} values, seed {} ({SEED_ENV}={} to replay)",
+            self.iterations, self.seed, self.seed
+        )?;
+        for failure in &self.failures {
+            let problem = match &failure.problem {
+                RoundTripProblem::Serialize(e) => format!("can't be written: {e}"),
+                RoundTripProblem::Deserialize(e) => format!("can't be read back: {e}"),
+                RoundTripProblem::Mismatch(diff) => format!("reads back differently:\n{diff}"),
+            };
+            writeln!(f, "\n{}: {problem}", failure.format)?;
+            if let Some(hint) = failure.hint {
+                writeln!(f, "  hint: {hint}")?;
+            }
+            writeln!(f, "  value: {}", failure.value)?;
+            if let Some(written) = &failure.written {
+                writeln!(f, "  written:\n{written}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Guesses the cause of a failure from the format and the error message.
+fn hint(format: ConfigFormat, message: &str) -> Option<&'static str> {
+    let message = message.to_lowercase();
+    if message.starts_with("invalid configuration") {
+        Some("the value breaks the validation rules, restrict what `Arbitrary` generates")
+    } else if matches!(format, ConfigFormat::Ini | ConfigFormat::Dotenv)
+        && (message.contains("flat") || message.contains("represent"))
+    {
+        Some("INI and dotenv only store scalars, one level of tables for INI")
+    } else if message.contains("none") || message.contains("null") || message.contains("unit") {
+        Some(
+            "the format has no null, skip `None` with #[serde(skip_serializing_if = \"Option::is_none\")]",
+        )
+    } else if message.contains("key") && (message.contains("string") || message.contains("map")) {
+        Some("map keys must be strings in this format")
+    } else if message.contains("out of range") || message.contains("too large") {
+        Some("integers are stored as i64, u64 and u128 values above i64::MAX don't fit")
+    } else if message.contains("nan") || message.contains("inf") {
+        Some("the format can't store NaN or infinite floats")
+    } else {
+        None
+    }
+}
+
+/// Round trips a single value through `format`.
+fn round_trip<T>(value: &T, format: ConfigFormat) -> Option<RoundTripFailure>
+where
+    T: Configuration + PartialEq + Debug,
+{
+    let fail = |written: Option<String>, problem: RoundTripProblem| {
+        let message = match &problem {
+            RoundTripProblem::Serialize(e) | RoundTripProblem::Deserialize(e) => e.clone(),
+            RoundTripProblem::Mismatch(_) => String::new(),
+        };
+        Some(RoundTripFailure {
+            format,
+            value: format!("{value:?}"),
+            written,
+            hint: hint(format, &message),
+            problem,
+        })
+    };
+
+    let written = match format.serialize(value) {
+        Ok(written) => written,
+        Err(e) => return fail(None, RoundTripProblem::Serialize(e.to_string())),
+    };
+    let read: T = match format.deserialize(&written) {
+        Ok(read) => read,
+        Err(e) => return fail(Some(written), RoundTripProblem::Deserialize(e.to_string())),
+    };
+    if &read != value {
+        let diff = ConfigDiff::between(value, &read)
+            .map(|diff| diff.to_text())
+            .unwrap_or_default();
+        return fail(Some(written), RoundTripProblem::Mismatch(diff));
+    }
+    None
+}
+
+/// Generates `iterations` random values of `T` and round trips each through
+/// every enabled or registered format, and through `save()` and `load()` in the declared
+/// format in an isolated [`TestConfigRoot`].
+///
+/// Values are generated from a random seed, or from [`SEED_ENV`] if set;
+/// the seed is part of the report. Values that `Arbitrary` can't build from
+/// the random bytes are skipped.
+pub fn check_round_trip<T>(iterations: usize) -> RoundTripReport
+where
+    T: Configuration + for<'a> Arbitrary<'a> + PartialEq + Debug,
+{
+    let seed = std::env::var(SEED_ENV)
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(|| fastrand::u64(..));
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let formats = FormatRegistry::formats();
+    let mut report = RoundTripReport {
+        seed,
+        iterations,
+        formats: formats.clone(),
+        failures: Vec::new(),
+    };
+
+    let root = TestConfigRoot::new();
+    for _ in 0..iterations {
+        let mut bytes = vec![0; rng.usize(0..4096)];
+        rng.fill(&mut bytes);
+        let Ok(value) = T::arbitrary_take_rest(Unstructured::new(&bytes)) else {
+            continue;
+        };
+
+        for format in &formats {
+            if report.failure(*format).is_some() {
+                continue;
+            }
+            if let Some(failure) = round_trip(&value, *format) {
+                report.failures.push(failure);
+            }
+        }
+
+        // The declared format through the real files, encryption included
+        if report.failure(T::format()).is_none() {
+            let problem = match value.save().map(|()| T::load()) {
+                Err(e) => Some(RoundTripProblem::Serialize(e.to_string())),
+                Ok(Err(e)) => Some(RoundTripProblem::Deserialize(e.to_string())),
+                Ok(Ok(loaded)) if loaded == value => None,
+                Ok(Ok(loaded)) => Some(RoundTripProblem::Mismatch(
+                    value
+                        .diff(&loaded)
+                        .map(|diff| diff.to_text())
+                        .unwrap_or_default(),
+                )),
+            };
+            if let Some(problem) = problem {
+                let (message, written) = match &problem {
+                    // A file left by an earlier value is not what failed
+                    RoundTripProblem::Serialize(e) => (e.as_str(), None),
+                    RoundTripProblem::Deserialize(e) => {
+                        (e.as_str(), std::fs::read_to_string(T::default_path()).ok())
+                    }
+                    RoundTripProblem::Mismatch(_) => {
+                        ("", std::fs::read_to_string(T::default_path()).ok())
+                    }
+                };
+                report.failures.push(RoundTripFailure {
+                    format: T::format(),
+                    value: format!("{value:?}"),
+                    written,
+                    hint: hint(T::format(), message),
+                    problem,
+                });
+            }
+        }
+    }
+    drop(root);
+    report
+}
+
+/// Like [`check_round_trip`], but panics with the report if a value fails
+/// in any enabled format.
+///
+/// # Panics
+/// If a round trip fails.
+pub fn assert_round_trip<T>(iterations: usize)
+where
+    T: Configuration + for<'a> Arbitrary<'a> + PartialEq + Debug,
+{
+    let report = check_round_trip::<T>(iterations);
+    assert!(
+        report.is_ok(),
+        "{} doesn't round trip: {report}",
+        T::config_name()
+    );
+}
@@ -0,0 +1,524 @@
+//! Human-friendly value types for configuration fields.
+//!
+//! Each type is written as a string in every format and parsed with an
+//! error message showing what was expected:
+//! - [`Duration`] as `30s`, `5m`, `1h30m`, `250ms`
+//! - [`ByteSize`] as `512B`, `10MiB`, `1.5GB`, or a number of bytes
+//! - [`ExpandedPath`] as `~/data`, `$HOME/data`, resolved against the
+//!   workspace root or the configuration file when relative
+//! - `Url` as `https://example.com/api` (`url` feature)
+//! - [`SocketAddr`] as `127.0.0.1:8080`, `[::1]:8080`, `localhost:8080`, `:8080`
+//!
+//! The schema of a field of one of these types, or an `Option` of one,
+//! describes the expected text in [`FieldSchema::value_format`](crate::FieldSchema::value_format).
+
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::schema::ValueFormat;
+use crate::{ConfigError, Configuration};
+
+/// Implements `Serialize` with `Display` and `Deserialize` from a string with `FromStr`.
+macro_rules! string_serde {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct StrVisitor;
+
+                impl Visitor<'_> for StrVisitor {
+                    type Value = $type;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str(<$type as ValueFormat>::FORMAT)
+                    }
+
+                    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                        value.parse().map_err(|e: ConfigError| match e {
+                            ConfigError::InvalidArgument(message) => E::custom(message),
+                            e => E::custom(e),
+                        })
+                    }
+                }
+
+                deserializer.deserialize_str(StrVisitor)
+            }
+        }
+    };
+}
+
+fn invalid(kind: &str, input: &str, reason: impl fmt::Display, format: &str) -> ConfigError {
+    ConfigError::InvalidArgument(format!(
+        "invalid {kind} `{input}`: {reason}, expected a {format}"
+    ))
+}
+
+/// Splits leading digits and dots from the rest of `input`.
+fn split_number(input: &str) -> (&str, &str) {
+    let end = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    input.split_at(end)
+}
+
+/// Expands `$VAR` and `${VAR}` with environment variables, `$$` is a literal `$`.
+///
+/// # Errors
+/// Why the text can't be expanded, e.g. an unset variable.
+pub(crate) fn expand_vars(input: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = input;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+        let (name, after) = if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        } else if let Some(braced) = rest.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| "`${` is not closed".to_string())?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            rest.split_at(end)
+        };
+        if name.is_empty() {
+            return Err("`$` is not followed by a variable name".to_string());
+        }
+        let value = crate::env_var(name).ok_or_else(|| format!("`${name}` is not set"))?;
+        expanded.push_str(&value);
+        rest = after;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// A length of time written as `30s`, `5m` or `1h30m`.
+///
+/// Units are `d`, `h`, `m`, `s`, `ms`, `us` and `ns`, and numbers may have
+/// decimals (`1.5h`). It is written back in the shortest form, like `1h30m`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(pub std::time::Duration);
+
+impl Duration {
+    /// Creates a duration of `secs` seconds.
+    pub const fn from_secs(secs: u64) -> Self {
+        Self(std::time::Duration::from_secs(secs))
+    }
+
+    /// Creates a duration of `millis` milliseconds.
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(std::time::Duration::from_millis(millis))
+    }
+}
+
+impl ValueFormat for Duration {
+    const FORMAT: &'static str = "duration like `30s`, `5m` or `1h30m`";
+}
+
+impl Deref for Duration {
+    type Target = std::time::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<std::time::Duration> for Duration {
+    fn from(duration: std::time::Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<Duration> for std::time::Duration {
+    fn from(duration: Duration) -> Self {
+        duration.0
+    }
+}
+
+const DURATION_UNITS: [(&str, u128); 7] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+impl FromStr for Duration {
+    type Err = ConfigError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| invalid("duration", input, reason, Self::FORMAT);
+        let mut rest = input.trim();
+        if rest.is_empty() {
+            return Err(error("it is empty".to_string()));
+        }
+        let mut nanos: f64 = 0.0;
+        while !rest.is_empty() {
+            let (number, after) = split_number(rest);
+            let value: f64 = number
+                .parse()
+                .map_err(|_| error(format!("`{rest}` doesn't start with a number")))?;
+            let after = after.trim_start();
+            let unit_end = after
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(after.len());
+            let (unit, after) = after.split_at(unit_end);
+            let per_unit = match unit {
+                "" => return Err(error(format!("`{number}` has no unit"))),
+                "µs" => 1_000,
+                unit => DURATION_UNITS
+                    .iter()
+                    .find(|(name, _)| *name == unit)
+                    .map(|(_, nanos)| *nanos)
+                    .ok_or_else(|| error(format!("unknown unit `{unit}`")))?,
+            };
+            nanos += value * per_unit as f64;
+            rest = after.trim_start();
+        }
+        if nanos > u64::MAX as f64 {
+            return Err(error("it is too long".to_string()));
+        }
+        Ok(Self(std::time::Duration::from_nanos(nanos.round() as u64)))
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nanos = self.0.as_nanos();
+        if nanos == 0 {
+            return f.write_str("0s");
+        }
+        for (unit, per_unit) in DURATION_UNITS {
+            if nanos >= per_unit {
+                write!(f, "{}{unit}", nanos / per_unit)?;
+                nanos %= per_unit;
+            }
+        }
+        Ok(())
+    }
+}
+
+string_serde!(Duration);
+
+/// A number of bytes written as `512B`, `10MiB` or `1.5GB`.
+///
+/// `KiB`, `MiB`, `GiB` and `TiB` are powers of 1024, `KB`, `MB`, `GB` and
+/// `TB` powers of 1000; units are case-insensitive and a plain number is a
+/// number of bytes. It is written back with the largest exact unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Creates a size of `n` KiB.
+    pub const fn kib(n: u64) -> Self {
+        Self(n * 1024)
+    }
+
+    /// Creates a size of `n` MiB.
+    pub const fn mib(n: u64) -> Self {
+        Self(n * 1024 * 1024)
+    }
+
+    /// Gets the number of bytes.
+    pub const fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl ValueFormat for ByteSize {
+    const FORMAT: &'static str = "size like `512B`, `10MiB` or `1GB`";
+}
+
+const BYTE_UNITS: [(&str, u64); 8] = [
+    ("TiB", 1 << 40),
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+    ("TB", 1_000_000_000_000),
+    ("GB", 1_000_000_000),
+    ("MB", 1_000_000),
+    ("KB", 1_000),
+];
+
+impl FromStr for ByteSize {
+    type Err = ConfigError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| invalid("size", input, reason, Self::FORMAT);
+        let (number, unit) = split_number(input.trim());
+        let value: f64 = number
+            .parse()
+            .map_err(|_| error("it doesn't start with a number".to_string()))?;
+        let unit = unit.trim();
+        let per_unit = match unit.to_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" => 1_000,
+            "m" => 1_000_000,
+            "g" => 1_000_000_000,
+            "t" => 1_000_000_000_000,
+            lower => BYTE_UNITS
+                .iter()
+                .find(|(name, _)| name.to_lowercase() == lower)
+                .map(|(_, bytes)| *bytes)
+                .ok_or_else(|| error(format!("unknown unit `{unit}`")))?,
+        };
+        let bytes = value * per_unit as f64;
+        if bytes > u64::MAX as f64 {
+            return Err(error("it is too large".to_string()));
+        }
+        Ok(Self(bytes.round() as u64))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let exact = BYTE_UNITS
+            .iter()
+            .find(|(_, per_unit)| self.0 != 0 && self.0.is_multiple_of(*per_unit));
+        match exact {
+            Some((unit, per_unit)) => write!(f, "{}{unit}", self.0 / per_unit),
+            None => write!(f, "{}B", self.0),
+        }
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteSizeVisitor;
+
+        impl Visitor<'_> for ByteSizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(ByteSize::FORMAT)
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(ByteSize(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                u64::try_from(value)
+                    .map(ByteSize)
+                    .map_err(|_| E::custom(format!("invalid size `{value}`: it is negative")))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value.parse().map_err(|e: ConfigError| match e {
+                    ConfigError::InvalidArgument(message) => E::custom(message),
+                    e => E::custom(e),
+                })
+            }
+        }
+
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
+
+/// A path with `~` and environment variables expanded.
+///
+/// `~` at the start is the home directory, `$VAR` and `${VAR}` are
+/// environment variables and `$$` is a literal `$`. Relative paths are
+/// resolved by [`ExpandedPath::path`] against the workspace root, or by
+/// [`ExpandedPath::relative_to_config`] against the configuration file. The
+/// path is written back as it was given, unexpanded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ExpandedPath {
+    raw: String,
+    expanded: PathBuf,
+}
+
+impl ExpandedPath {
+    /// Gets the path as written in the configuration.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Gets the expanded path, which may be relative.
+    pub fn expanded(&self) -> &Path {
+        &self.expanded
+    }
+
+    /// Gets the expanded path, resolved against `base` if it is relative.
+    pub fn relative_to(&self, base: &Path) -> PathBuf {
+        base.join(&self.expanded)
+    }
+
+    /// Gets the expanded path, resolved against the workspace root if it is
+    /// relative, see [`crate::template::workspace_root`].
+    pub fn path(&self) -> PathBuf {
+        self.relative_to(&crate::template::workspace_root())
+    }
+
+    /// Gets the expanded path, resolved against the directory of the
+    /// configuration file of `T` if it is relative.
+    pub fn relative_to_config<T: Configuration>(&self) -> PathBuf {
+        let path = T::default_path();
+        self.relative_to(path.parent().unwrap_or(Path::new("")))
+    }
+}
+
+impl ValueFormat for ExpandedPath {
+    const FORMAT: &'static str = "path, `~` and `$VAR` are expanded";
+}
+
+impl FromStr for ExpandedPath {
+    type Err = ConfigError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| invalid("path", input, reason, Self::FORMAT);
+        let mut expanded = String::new();
+        let mut rest = input;
+        if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+            let home = crate::env_var("HOME")
+                .or_else(|| crate::env_var("USERPROFILE"))
+                .ok_or_else(|| error("the home directory is unknown".to_string()))?;
+            expanded.push_str(&home);
+            rest = &rest[1..];
+        }
+        expanded.push_str(&expand_vars(rest).map_err(error)?);
+        Ok(Self {
+            raw: input.to_string(),
+            expanded: PathBuf::from(expanded),
+        })
+    }
+}
+
+impl fmt::Display for ExpandedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl AsRef<Path> for ExpandedPath {
+    fn as_ref(&self) -> &Path {
+        &self.expanded
+    }
+}
+
+string_serde!(ExpandedPath);
+
+/// An absolute URL like `https://example.com/api`.
+#[cfg(feature = "url")]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Url(pub url::Url);
+
+#[cfg(feature = "url")]
+impl ValueFormat for Url {
+    const FORMAT: &'static str = "URL like `https://example.com`";
+}
+
+#[cfg(feature = "url")]
+impl Deref for Url {
+    type Target = url::Url;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "url")]
+impl FromStr for Url {
+    type Err = ConfigError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        url::Url::parse(input.trim())
+            .map(Self)
+            .map_err(|e| invalid("URL", input, e, Self::FORMAT))
+    }
+}
+
+#[cfg(feature = "url")]
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "url")]
+string_serde!(Url);
+
+/// A socket address written as `127.0.0.1:8080` or `[::1]:8080`.
+///
+/// `localhost:8080` is read as `127.0.0.1:8080` and `:8080` as
+/// `0.0.0.0:8080`, every interface; no other host name is resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddr(pub std::net::SocketAddr);
+
+impl ValueFormat for SocketAddr {
+    const FORMAT: &'static str = "address like `127.0.0.1:8080`, `[::1]:8080` or `:8080`";
+}
+
+impl Default for SocketAddr {
+    fn default() -> Self {
+        Self(std::net::SocketAddr::from(([127, 0, 0, 1], 0)))
+    }
+}
+
+impl Deref for SocketAddr {
+    type Target = std::net::SocketAddr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<std::net::SocketAddr> for SocketAddr {
+    fn from(addr: std::net::SocketAddr) -> Self {
+        Self(addr)
+    }
+}
+
+impl FromStr for SocketAddr {
+    type Err = ConfigError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        let normalized = if let Some(port) = trimmed.strip_prefix(':') {
+            format!("0.0.0.0:{port}")
+        } else if let Some(port) = trimmed.strip_prefix("localhost:") {
+            format!("127.0.0.1:{port}")
+        } else {
+            trimmed.to_string()
+        };
+        normalized.parse().map(Self).map_err(|_| {
+            let reason = if trimmed.contains(':') {
+                "the host must be an IP address or `localhost`"
+            } else {
+                "the port is missing"
+            };
+            invalid("socket address", input, reason, Self::FORMAT)
+        })
+    }
+}
+
+impl fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+string_serde!(SocketAddr);
@@ -24,6 +24,7 @@ struct CustomNameConfig {
 }
 
 // Create a test configuration with a custom format
+#[cfg(feature = "json")]
 #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
 #[cfg_attr(test, derive(mtc_config::Configuration))]
 #[config(name = "json_config", format = "json")]
@@ -55,11 +56,8 @@ struct TestConfigForSaveLoad {
 
 #[test]
 fn test_save_and_load() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
 
-
-    // Save the config using our temp directory
-    //    let tmpdir= std::env::var("CONFIG_ROOT_DIR").unwrap();
-    //    dbg!(tmpdir);
     // Create a test config
     let config = TestConfigForSaveLoad {
         name: "Test".to_string(),
@@ -94,6 +92,7 @@ struct NonexistentConfig {
 
 #[test]
 fn test_load_nonexistent() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
     
     // Try to load a config that doesn't exist
     let result =  NonexistentConfig::load();
@@ -104,6 +103,7 @@ fn test_load_nonexistent() {
 
 #[test]
 fn test_load_or_default() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
 
     // Use load_or_default to get a config (should create a default)
         {
@@ -132,6 +132,7 @@ fn test_load_or_default() {
 
 #[test]
 fn test_custom_config_name() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
 
     // Create a custom named config
     let config = CustomNameConfig {
@@ -155,6 +156,7 @@ fn test_custom_config_name() {
 
 #[test]
 fn test_load_or_default_with_modification() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
 
     // First ensure we have a default config saved
   
@@ -178,6 +180,7 @@ fn test_load_or_default_with_modification() {
 #[cfg(feature = "json")]
 #[test]
 fn test_custom_format() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
 
     // Create a config with custom format
     let config = JsonFormatConfig {
@@ -204,11 +207,1715 @@ fn test_config_format_default() {
 
 #[test]
 fn test_config_format_from_string() {
-    assert_eq!(ConfigFormat::from("json"), ConfigFormat::Json);
-    assert_eq!(ConfigFormat::from("toml"), ConfigFormat::Toml);
-    assert_eq!(ConfigFormat::from("yaml"), ConfigFormat::Yaml);
-    // Unknown formats should default to TOML
-    assert_eq!(ConfigFormat::from("unknown"), ConfigFormat::Toml);
+    assert_eq!("json".parse::<ConfigFormat>().unwrap(), ConfigFormat::Json);
+    assert_eq!("toml".parse::<ConfigFormat>().unwrap(), ConfigFormat::Toml);
+    assert_eq!("yaml".parse::<ConfigFormat>().unwrap(), ConfigFormat::Yaml);
+    assert_eq!("ron".parse::<ConfigFormat>().unwrap(), ConfigFormat::Ron);
+    assert_eq!("json5".parse::<ConfigFormat>().unwrap(), ConfigFormat::Json5);
+    assert_eq!("ini".parse::<ConfigFormat>().unwrap(), ConfigFormat::Ini);
+    assert_eq!("dotenv".parse::<ConfigFormat>().unwrap(), ConfigFormat::Dotenv);
+    assert_eq!(ConfigFormat::try_from("yml").unwrap(), ConfigFormat::Yaml);
+    // Unknown formats are an error instead of silently becoming TOML
+    assert!(matches!(
+        ConfigFormat::try_from("unknown"),
+        Err(ConfigError::UnknownFormat(f)) if f == "unknown"
+    ));
+}
+
+#[test]
+fn test_config_format_names_round_trip() {
+    for format in ConfigFormat::ALL {
+        assert_eq!(format.to_string().parse::<ConfigFormat>().unwrap(), format);
+        assert_eq!(format.extension().parse::<ConfigFormat>().unwrap(), format);
+    }
+    assert_eq!(ConfigFormat::Dotenv.extension(), "env");
+}
+
+#[cfg(any(feature = "ini", feature = "dotenv"))]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct FlatConfig {
+    name: String,
+    port: u16,
+    ratio: f64,
+    enabled: bool,
+    numeric_string: String,
+    tricky: String,
+    missing: Option<String>,
+}
+
+#[cfg(any(feature = "ini", feature = "dotenv"))]
+fn flat_config() -> FlatConfig {
+    FlatConfig {
+        name: "service".to_string(),
+        port: 8080,
+        ratio: 0.5,
+        enabled: true,
+        numeric_string: "42".to_string(),
+        tricky: " padded \"quoted\" # not a comment\n".to_string(),
+        missing: None,
+    }
+}
+
+#[cfg(any(feature = "json5", feature = "ini", feature = "dotenv"))]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct SectionedConfig {
+    title: String,
+    server: ServerSection,
+}
+
+#[cfg(any(feature = "json5", feature = "ini", feature = "dotenv"))]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct ServerSection {
+    host: String,
+    port: u16,
+}
+
+#[cfg(feature = "ron")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "ron_config", format = "ron")]
+struct RonFormatConfig {
+    ron_field: String,
+    values: Vec<u32>,
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn test_ron_format() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = RonFormatConfig {
+        ron_field: "RON Value".to_string(),
+        values: vec![1, 2, 3],
+    };
+    config.save().expect("Failed to save RON format config");
+
+    let config_path = RonFormatConfig::default_path();
+    assert_eq!(config_path.extension().unwrap(), "ron");
+    assert_eq!(RonFormatConfig::load().unwrap(), config);
+}
+
+#[cfg(feature = "json5")]
+#[test]
+fn test_json5_format() {
+    let content = "{ title: 'hand written', // comment\n server: { host: 'localhost', port: 80, }, }";
+    let config: SectionedConfig = ConfigFormat::Json5.deserialize(content).unwrap();
+    assert_eq!(config.title, "hand written");
+    assert_eq!(config.server.port, 80);
+
+    let written = ConfigFormat::Json5.serialize(&config).unwrap();
+    assert_eq!(ConfigFormat::Json5.deserialize::<SectionedConfig>(&written).unwrap(), config);
+}
+
+#[cfg(feature = "ini")]
+#[test]
+fn test_ini_format() {
+    let config = SectionedConfig {
+        title: "my app".to_string(),
+        server: ServerSection {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+        },
+    };
+    let written = ConfigFormat::Ini.serialize(&config).unwrap();
+    assert_eq!(written, "title = my app\n\n[server]\nhost = 0.0.0.0\nport = 3000\n");
+    assert_eq!(ConfigFormat::Ini.deserialize::<SectionedConfig>(&written).unwrap(), config);
+
+    let flat = flat_config();
+    let written = ConfigFormat::Ini.serialize(&flat).unwrap();
+    assert_eq!(ConfigFormat::Ini.deserialize::<FlatConfig>(&written).unwrap(), flat);
+}
+
+#[cfg(feature = "ini")]
+#[test]
+fn test_ini_rejects_deep_nesting() {
+    #[derive(Serialize)]
+    struct Deep {
+        a: std::collections::BTreeMap<String, ServerSection>,
+    }
+    let mut a = std::collections::BTreeMap::new();
+    a.insert("b".to_string(), ServerSection::default());
+    let result = ConfigFormat::Ini.serialize(&Deep { a });
+    assert!(matches!(result, Err(ConfigError::Serialization(msg)) if msg.contains("a.b")));
+}
+
+#[cfg(feature = "dotenv")]
+#[test]
+fn test_dotenv_format() {
+    let flat = flat_config();
+    let written = ConfigFormat::Dotenv.serialize(&flat).unwrap();
+    assert!(written.contains("PORT=8080\n"));
+    assert!(written.contains("NUMERIC_STRING=\"42\"\n"));
+    assert!(!written.contains("MISSING"));
+    assert_eq!(ConfigFormat::Dotenv.deserialize::<FlatConfig>(&written).unwrap(), flat);
+
+    let hand_written = "# comment\nexport NAME=svc\nPORT=1\nRATIO=2.5\nENABLED=false\nNUMERIC_STRING='7'\nTRICKY=x\n";
+    let loaded: FlatConfig = ConfigFormat::Dotenv.deserialize(hand_written).unwrap();
+    assert_eq!(loaded.name, "svc");
+    assert_eq!(loaded.numeric_string, "7");
+}
+
+#[cfg(feature = "dotenv")]
+#[test]
+fn test_dotenv_rejects_nested_config() {
+    let result = ConfigFormat::Dotenv.serialize(&SectionedConfig::default());
+    assert!(matches!(result, Err(ConfigError::Serialization(msg)) if msg.contains("`server`")));
+}
+
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "fallback_config", format = "json")]
+struct FallbackConfig {
+    value: i32,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "migrated_config", format = "json", migrate)]
+struct MigratedConfig {
+    value: i32,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "converted_config")]
+struct ConvertedConfig {
+    value: i32,
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_load_falls_back_to_other_extensions() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let toml_path = FallbackConfig::path_for(ConfigFormat::Toml);
+    std::fs::write(&toml_path, "value = 7\n").unwrap();
+
+    assert_eq!(
+        FallbackConfig::find_existing(),
+        Some((toml_path.clone(), ConfigFormat::Toml))
+    );
+    assert_eq!(FallbackConfig::load().unwrap(), FallbackConfig { value: 7 });
+    // Without `migrate` the file is left as it is
+    assert!(toml_path.exists());
+    assert!(!FallbackConfig::default_path().exists());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_load_migrates_other_extensions() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let toml_path = MigratedConfig::path_for(ConfigFormat::Toml);
+    std::fs::write(&toml_path, "value = 8\n").unwrap();
+
+    assert_eq!(MigratedConfig::load().unwrap(), MigratedConfig { value: 8 });
+    assert!(!toml_path.exists());
+    let content = std::fs::read_to_string(MigratedConfig::default_path()).unwrap();
+    assert!(content.contains("\"value\": 8"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_convert_to() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    assert!(matches!(
+        ConvertedConfig::convert_to(ConfigFormat::Json),
+        Err(ConfigError::NotFound(_))
+    ));
+
+    ConvertedConfig { value: 9 }.save().unwrap();
+    let json_path = ConvertedConfig::convert_to(ConfigFormat::Json).unwrap();
+    assert_eq!(json_path.extension().unwrap(), "json");
+    assert!(json_path.exists());
+    assert!(!ConvertedConfig::default_path().exists());
+    // The declared TOML file is gone, so load() falls back to the JSON one
+    assert_eq!(ConvertedConfig::load().unwrap(), ConvertedConfig { value: 9 });
+
+    let toml_path = ConvertedConfig::convert_to(ConfigFormat::Toml).unwrap();
+    assert_eq!(toml_path, ConvertedConfig::default_path());
+    assert!(!json_path.exists());
+    assert_eq!(ConvertedConfig::load().unwrap(), ConvertedConfig { value: 9 });
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "history_config", history = 2)]
+struct HistoryConfig {
+    value: i32,
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_convert_to_keeps_history() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    HistoryConfig { value: 1 }.save().unwrap();
+    root.seed_file("history_config.json", "{\"value\": 0}");
+
+    HistoryConfig::convert_to(ConfigFormat::Json).unwrap();
+    let history = HistoryConfig::history().unwrap();
+    let formats: Vec<_> = history.iter().map(|version| version.format).collect();
+    assert_eq!(formats, [ConfigFormat::Json, ConfigFormat::Toml]);
+    assert_eq!(std::fs::read_to_string(&history[1].path).unwrap(), "value = 1\n");
+
+    let restored = HistoryConfig::restore(&history[0].id).unwrap();
+    assert_eq!(restored, HistoryConfig { value: 0 });
+}
+
+#[test]
+fn test_history_and_restore() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    assert!(HistoryConfig::history().unwrap().is_empty());
+
+    for value in 1..=4 {
+        HistoryConfig { value }.save().unwrap();
+    }
+    // Saving unchanged content doesn't add a version
+    HistoryConfig { value: 4 }.save().unwrap();
+
+    let history = HistoryConfig::history().unwrap();
+    assert_eq!(history.len(), 2, "only the newest versions are kept");
+    assert!(history[0].id > history[1].id, "history is listed newest first");
+    assert!(history[0].timestamp().is_some());
+    assert_eq!(std::fs::read_to_string(&history[0].path).unwrap(), "value = 3\n");
+    assert_eq!(std::fs::read_to_string(&history[1].path).unwrap(), "value = 2\n");
+
+    let diff = HistoryConfig::diff_versions(&history[1].id, mtc_config::CURRENT_VERSION).unwrap();
+    assert_eq!(diff, "-value = 2\n+value = 4\n");
+
+    let restored = HistoryConfig::restore(&history[1].id).unwrap();
+    assert_eq!(restored, HistoryConfig { value: 2 });
+    assert_eq!(HistoryConfig::load().unwrap(), HistoryConfig { value: 2 });
+
+    // The version replaced by the restore is itself kept
+    let history = HistoryConfig::history().unwrap();
+    assert_eq!(std::fs::read_to_string(&history[0].path).unwrap(), "value = 4\n");
+
+    assert!(matches!(
+        HistoryConfig::restore("nope"),
+        Err(ConfigError::VersionNotFound(id)) if id == "nope"
+    ));
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "diff_config")]
+struct DiffConfig {
+    name: String,
+    port: u16,
+    tags: Vec<String>,
+    proxy: Option<String>,
+    limits: DiffLimits,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+struct DiffLimits {
+    max: u32,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            name: "svc".to_string(),
+            port: 80,
+            tags: vec!["a".to_string()],
+            proxy: None,
+            limits: DiffLimits { max: 1 },
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    use mtc_config::ChangeKind;
+
+    let old = DiffConfig::default();
+    let new = DiffConfig {
+        port: 8080,
+        tags: vec![],
+        proxy: Some("http://proxy".to_string()),
+        limits: DiffLimits { max: 2 },
+        ..old.clone()
+    };
+    assert!(old.diff(&old).unwrap().is_empty());
+
+    let diff = old.diff(&new).unwrap();
+    let summary: Vec<(&str, ChangeKind)> = diff
+        .changes
+        .iter()
+        .map(|c| (c.path.as_str(), c.kind))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("limits.max", ChangeKind::Changed),
+            ("port", ChangeKind::Changed),
+            ("proxy", ChangeKind::Added),
+            ("tags[0]", ChangeKind::Removed),
+        ]
+    );
+    assert_eq!(
+        diff.to_text(),
+        "~ limits.max: 1 -> 2\n~ port: 80 -> 8080\n+ proxy = \"http://proxy\"\n- tags[0] = \"a\"\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+    assert_eq!(json[1]["path"], "port");
+    assert_eq!(json[1]["kind"], "changed");
+    assert_eq!(json[1]["old"], 80);
+    assert_eq!(json[1]["new"], 8080);
+
+    assert_eq!(new.diff_default().unwrap(), diff);
+}
+
+#[test]
+fn test_diff_saved() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = DiffConfig::default();
+    // Nothing saved yet, so every leaf is added
+    let paths: Vec<String> = config
+        .diff_saved()
+        .unwrap()
+        .changes
+        .into_iter()
+        .map(|c| c.path)
+        .collect();
+    assert_eq!(paths, vec!["limits.max", "name", "port", "tags[0]"]);
+
+    config.save().unwrap();
+    assert!(config.diff_saved().unwrap().is_empty());
+
+    let changed = DiffConfig {
+        name: "other".to_string(),
+        ..config
+    };
+    assert_eq!(changed.diff_saved().unwrap().to_text(), "~ name: \"svc\" -> \"other\"\n");
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "registry_config")]
+struct RegistryConfig {
+    value: i32,
+}
+
+#[test]
+fn test_registry() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let names: Vec<String> = mtc_config::registered_configs()
+        .iter()
+        .map(|config| config.config_name())
+        .collect();
+    assert!(names.contains(&"registry_config".to_string()));
+    assert!(names.contains(&"custom_config".to_string()));
+    assert!(names.windows(2).all(|pair| pair[0] <= pair[1]), "sorted by name");
+
+    let registered = mtc_config::find_registered_config("registry_config").unwrap();
+    assert!(registered.type_name.ends_with("::RegistryConfig"));
+    assert_eq!(registered.format(), ConfigFormat::Toml);
+    assert_eq!(registered.default_path(), RegistryConfig::default_path());
+    assert_eq!(registered.defaults().unwrap(), serde_json::json!({ "value": 0 }));
+
+    RegistryConfig { value: 5 }.save().unwrap();
+    registered.validate().unwrap();
+    assert_eq!(registered.load().unwrap(), serde_json::json!({ "value": 5 }));
+
+    std::fs::write(RegistryConfig::default_path(), "value = \"not a number\"\n").unwrap();
+    assert!(matches!(registered.validate(), Err(ConfigError::Deserialization(_))));
+
+    registered.reset().unwrap();
+    assert_eq!(RegistryConfig::load().unwrap(), RegistryConfig::default());
+
+    assert!(mtc_config::find_registered_config("no_such_config").is_none());
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "global_config", global)]
+struct GlobalTestConfig {
+    value: i32,
+}
+
+#[test]
+fn test_global() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    GlobalTestConfig { value: 1 }.save().unwrap();
+    let first = GlobalTestConfig::global();
+    assert_eq!(first.value, 1);
+    assert!(std::sync::Arc::ptr_eq(&first, &GlobalTestConfig::global()));
+
+    // The shared value is only re-read on request
+    GlobalTestConfig { value: 2 }.save().unwrap();
+    assert_eq!(GlobalTestConfig::global().value, 1);
+    assert_eq!(GlobalTestConfig::reload_global().unwrap().value, 2);
+    assert_eq!(GlobalTestConfig::global().value, 2);
+    assert_eq!(first.value, 1, "values handed out earlier are untouched");
+
+    {
+        let _guard = GlobalTestConfig::override_global(GlobalTestConfig { value: 99 });
+        assert_eq!(GlobalTestConfig::global().value, 99);
+        assert_eq!(GlobalTestConfig::load().unwrap().value, 2, "disk is untouched");
+    }
+    assert_eq!(GlobalTestConfig::global().value, 2);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+struct HttpServerConfig {
+    port: u16,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+struct HTTPServerSettings {
+    port: u16,
+}
+
+#[test]
+fn test_default_config_name_is_snake_case() {
+    assert_eq!(HttpServerConfig::config_name(), "http_server_config");
+    assert_eq!(HTTPServerSettings::config_name(), "http_server_settings");
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "generic_config")]
+struct GenericConfig<T>
+where
+    T: Clone,
+{
+    inner: T,
+    enabled: bool,
+}
+
+#[test]
+fn test_generic_config() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = GenericConfig {
+        inner: ServerLimits { max: 3 },
+        enabled: true,
+    };
+    config.save().unwrap();
+    assert_eq!(GenericConfig::<ServerLimits>::load().unwrap(), config);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+struct ServerLimits {
+    max: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "storage_backend")]
+#[serde(tag = "backend", rename_all = "lowercase")]
+enum StorageBackend {
+    Local { path: String },
+    S3 { bucket: String, region: String },
+}
+
+impl Default for StorageBackend {
+    fn default() -> Self {
+        StorageBackend::Local {
+            path: "/tmp".to_string(),
+        }
+    }
+}
+
+#[test]
+fn test_enum_config() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = StorageBackend::S3 {
+        bucket: "configs".to_string(),
+        region: "eu-west-1".to_string(),
+    };
+    config.save().unwrap();
+    let content = std::fs::read_to_string(StorageBackend::default_path()).unwrap();
+    assert!(content.contains("backend = \"s3\""));
+    assert_eq!(StorageBackend::load().unwrap(), config);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "newtype_config")]
+struct NewtypeConfig(ServerLimits);
+
+#[test]
+fn test_newtype_config() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = NewtypeConfig(ServerLimits { max: 7 });
+    config.save().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(NewtypeConfig::default_path()).unwrap(),
+        "max = 7\n"
+    );
+    assert_eq!(NewtypeConfig::load().unwrap(), config);
+}
+
+/// Connection settings of the schema test.
+#[derive(Serialize, Deserialize, Debug, PartialEq, mtc_config::ConfigSection)]
+struct SchemaServer {
+    /// Address to listen on.
+    host: String,
+    #[config(min = 1, max = 65535)]
+    port: u32,
+}
+
+/// Configuration used to test the generated schema.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "schema_config")]
+struct SchemaConfig {
+    /// Log level of the service.
+    #[config(one_of = ["debug", "info"])]
+    level: String,
+    #[config(secret, non_empty)]
+    #[serde(rename = "api-token")]
+    token: String,
+    #[config(nested)]
+    server: SchemaServer,
+    tags: Vec<String>,
+    #[serde(skip)]
+    cache: Option<u32>,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        SchemaConfig {
+            level: "info".to_string(),
+            token: "change-me".to_string(),
+            server: SchemaServer {
+                host: "localhost".to_string(),
+                port: 8080,
+            },
+            tags: Vec::new(),
+            cache: None,
+        }
+    }
+}
+
+#[test]
+fn test_schema() {
+    let schema = SchemaConfig::schema();
+    assert_eq!(schema.type_name, "SchemaConfig");
+    assert_eq!(schema.doc, "Configuration used to test the generated schema.");
+    let names: Vec<_> = schema.fields.iter().map(|field| field.name).collect();
+    assert_eq!(names, ["level", "api-token", "server", "tags"]);
+
+    let level = schema.field("level").unwrap();
+    assert_eq!(level.type_name, "String");
+    assert_eq!(level.doc, "Log level of the service.");
+    assert_eq!(level.default.as_deref(), Some("\"info\""));
+    assert_eq!(
+        level.rules,
+        [mtc_config::ValidationRule::OneOf(vec!["debug", "info"])]
+    );
+    assert_eq!(schema.field("tags").unwrap().type_name, "Vec<String>");
+    assert_eq!(schema.secret_paths(), ["api-token"]);
+
+    let server = schema.field("server").unwrap().nested.as_ref().unwrap();
+    assert_eq!(server.doc, "Connection settings of the schema test.");
+    let port = server.field("port").unwrap();
+    assert_eq!(port.default.as_deref(), Some("8080"));
+    assert_eq!(
+        port.rules,
+        [
+            mtc_config::ValidationRule::Min(1.0),
+            mtc_config::ValidationRule::Max(65535.0)
+        ]
+    );
+    assert!(TestConfig::schema().field("value").unwrap().rules.is_empty());
+}
+
+#[test]
+fn test_schema_validation_on_load() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    SchemaConfig::default().save().unwrap();
+    assert!(SchemaConfig::load().is_ok());
+
+    let config = SchemaConfig {
+        level: "trace".to_string(),
+        token: String::new(),
+        server: SchemaServer {
+            host: "localhost".to_string(),
+            port: 0,
+        },
+        ..Default::default()
+    };
+    config.save().unwrap();
+    match SchemaConfig::load() {
+        Err(ConfigError::Validation(message)) => {
+            assert!(message.contains("`level` must be one of: debug, info"));
+            assert!(message.contains("`api-token` must not be empty"));
+            assert!(message.contains("`server.port` must be at least 1"));
+        }
+        other => panic!("expected a validation error, got {other:?}"),
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "embedded_config", default_file = "tests/defaults/embedded_config.toml")]
+struct EmbeddedConfig {
+    greeting: String,
+    retries: u32,
+}
+
+#[test]
+fn test_default_file() {
+    let _root = mtc_config::testing::TestConfigRoot::new();
+    let config = EmbeddedConfig::load_or_default();
+    assert_eq!(
+        config,
+        EmbeddedConfig {
+            greeting: "hello".to_string(),
+            retries: 3,
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(EmbeddedConfig::default_path()).unwrap(),
+        include_str!("defaults/embedded_config.toml")
+    );
+    assert!(TestConfig::embedded_default().unwrap().is_none());
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi_round_trip() {
+    use mtc_config::ffi::*;
+    use std::ffi::{CStr, CString, c_char};
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let c = |s: &str| CString::new(s).unwrap();
+    root.seed_file("ffi_config.toml", "name = \"demo\"\n\n[server]\nport = 8080\ntls = false\n");
+    let path = root.path().join("ffi_config.toml");
+
+    unsafe {
+        let config = mtc_config_load(c("ffi_config").as_ptr(), c("toml").as_ptr());
+        assert!(!config.is_null());
+
+        let mut name: *mut c_char = std::ptr::null_mut();
+        assert_eq!(mtc_config_get_string(config, c("name").as_ptr(), &mut name), 0);
+        assert_eq!(CStr::from_ptr(name).to_str().unwrap(), "demo");
+        mtc_config_string_free(name);
+
+        let mut port = 0i64;
+        assert_eq!(mtc_config_get_int(config, c("server.port").as_ptr(), &mut port), 0);
+        assert_eq!(port, 8080);
+
+        let mut tls = true;
+        assert_eq!(mtc_config_get_bool(config, c("server.tls").as_ptr(), &mut tls), 0);
+        assert!(!tls);
+
+        assert_eq!(mtc_config_get_int(config, c("name").as_ptr(), &mut port), -1);
+        let error = CStr::from_ptr(mtc_config_last_error()).to_str().unwrap();
+        assert!(error.contains("`name` is not an integer"), "{error}");
+
+        assert_eq!(mtc_config_set_int(config, c("server.port").as_ptr(), 9090), 0);
+        assert_eq!(mtc_config_set_bool(config, c("server.tls").as_ptr(), true), 0);
+        assert_eq!(mtc_config_set_string(config, c("owner.email").as_ptr(), c("a@b.c").as_ptr()), 0);
+        assert_eq!(mtc_config_save(config), 0);
+        mtc_config_free(config);
+
+        let missing = mtc_config_load(c("ffi_missing").as_ptr(), c("toml").as_ptr());
+        assert!(missing.is_null());
+        let unknown = mtc_config_load(c("ffi_config").as_ptr(), c("xml").as_ptr());
+        assert!(unknown.is_null());
+        let escaping = mtc_config_open(c("../ffi_config").as_ptr(), c("toml").as_ptr());
+        assert!(escaping.is_null());
+        let error = CStr::from_ptr(mtc_config_last_error()).to_string_lossy();
+        assert!(error.contains("invalid configuration name"), "{error}");
+
+        // A table of a shared file is opened through the shared file
+        let table = mtc_config_load(c("shared_docs").as_ptr(), std::ptr::null());
+        assert!(table.is_null());
+        let error = CStr::from_ptr(mtc_config_last_error()).to_string_lossy();
+        assert!(error.contains("open `shared_ws` instead"), "{error}");
+
+        // Without a format, any fallback extension is found and kept
+        #[cfg(feature = "yaml")]
+        {
+            root.seed_file("ffi_yaml.yml", "name: short
+");
+            let yaml = mtc_config_load(c("ffi_yaml").as_ptr(), std::ptr::null());
+            assert!(!yaml.is_null());
+            assert_eq!(mtc_config_get_string(yaml, c("name").as_ptr(), &mut name), 0);
+            assert_eq!(CStr::from_ptr(name).to_str().unwrap(), "short");
+            mtc_config_string_free(name);
+            assert_eq!(mtc_config_save(yaml), 0);
+            mtc_config_free(yaml);
+            assert!(!root.path().join("ffi_yaml.yaml").exists());
+        }
+
+        let saved = mtc_config_load(c("ffi_config").as_ptr(), std::ptr::null());
+        assert_eq!(mtc_config_get_int(saved, c("server.port").as_ptr(), &mut port), 0);
+        assert_eq!(port, 9090);
+        assert_eq!(mtc_config_get_bool(saved, c("server.tls").as_ptr(), &mut tls), 0);
+        assert!(tls);
+        mtc_config_free(saved);
+    }
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("[owner]\nemail = \"a@b.c\""), "{saved}");
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn test_ffi_header_is_up_to_date() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(format!("{dir}/cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::generate_with_config(dir, config)
+        .unwrap()
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+    let path = format!("{dir}/include/mtc_config.h");
+    if std::env::var_os("MTC_CONFIG_BLESS").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+    }
+    assert!(
+        std::fs::read_to_string(&path).unwrap() == generated,
+        "include/mtc_config.h is out of date, regenerate it with MTC_CONFIG_BLESS=1"
+    );
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "encrypted_config", encrypted, history = 2)]
+struct EncryptedConfig {
+    user: String,
+    password: String,
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encrypted_config() {
+    use mtc_config::encryption::KEY_ENV;
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    root.set_env(KEY_ENV, key);
+    let config = EncryptedConfig {
+        user: "admin".to_string(),
+        password: "hunter2".to_string(),
+    };
+    config.save().unwrap();
+
+    let content = std::fs::read_to_string(EncryptedConfig::default_path()).unwrap();
+    assert!(mtc_config::encryption::is_sealed(&content));
+    assert!(!content.contains("hunter2"));
+    assert_eq!(EncryptedConfig::load().unwrap(), config);
+
+    // Another key can't open it
+    root.set_env(KEY_ENV, &"ff".repeat(32));
+    assert!(matches!(
+        EncryptedConfig::load(),
+        Err(ConfigError::Encryption(_))
+    ));
+    root.set_env(KEY_ENV, key);
+
+    // Saving unchanged content must not create a backup despite the new nonce
+    config.save().unwrap();
+    assert!(EncryptedConfig::history().unwrap().is_empty());
+
+    // Flipping a byte of the ciphertext fails authentication
+    let mut tampered: Vec<char> = content.chars().collect();
+    let last = tampered.len() - 2;
+    tampered[last] = if tampered[last] == '0' { '1' } else { '0' };
+    std::fs::write(
+        EncryptedConfig::default_path(),
+        tampered.into_iter().collect::<String>(),
+    )
+    .unwrap();
+    assert!(matches!(
+        EncryptedConfig::load(),
+        Err(ConfigError::Encryption(_))
+    ));
+}
+
+#[cfg(feature = "integrity")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "integrity_config", integrity)]
+struct IntegrityConfig {
+    replicas: u32,
+}
+
+#[cfg(feature = "integrity")]
+#[test]
+fn test_integrity_sidecar() {
+    use mtc_config::integrity::{KEY_ENV, KEY_FILE_ENV};
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.remove_env(KEY_ENV).remove_env(KEY_FILE_ENV);
+    let path = IntegrityConfig::default_path();
+    let sidecar = mtc_config::integrity::sidecar_path(&path);
+    IntegrityConfig { replicas: 2 }.save().unwrap();
+    assert!(std::fs::read_to_string(&sidecar).unwrap().starts_with("sha256:"));
+    assert_eq!(IntegrityConfig::load().unwrap().replicas, 2);
+
+    // An edit outside of save() is detected until it is accepted
+    std::fs::write(&path, "replicas = 5\n").unwrap();
+    assert!(matches!(
+        IntegrityConfig::load(),
+        Err(ConfigError::IntegrityMismatch(_))
+    ));
+    IntegrityConfig::reseal().unwrap();
+    assert_eq!(IntegrityConfig::load().unwrap().replicas, 5);
+    IntegrityConfig::verify_integrity().unwrap();
+
+    // A broken edit is not accepted and a missing sidecar is a mismatch
+    std::fs::write(&path, "replicas = \"many\"\n").unwrap();
+    assert!(IntegrityConfig::reseal().is_err());
+    IntegrityConfig { replicas: 1 }.save().unwrap();
+    std::fs::remove_file(&sidecar).unwrap();
+    assert!(matches!(
+        IntegrityConfig::load(),
+        Err(ConfigError::IntegrityMismatch(_))
+    ));
+}
+
+#[cfg(feature = "integrity")]
+#[test]
+fn test_integrity_sidecar_with_key() {
+    use mtc_config::integrity::{KEY_ENV, KEY_FILE_ENV};
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.set_env(KEY_ENV, "secret").remove_env(KEY_FILE_ENV);
+    let sidecar = mtc_config::integrity::sidecar_path(&IntegrityConfig::default_path());
+    IntegrityConfig { replicas: 3 }.save().unwrap();
+    assert!(std::fs::read_to_string(&sidecar).unwrap().starts_with("hmac-sha256:"));
+    assert_eq!(IntegrityConfig::load().unwrap().replicas, 3);
+
+    // The key file gives the same checksum, surrounding whitespace aside
+    root.seed_file("integrity.key", "secret\n")
+        .remove_env(KEY_ENV)
+        .set_env(KEY_FILE_ENV, root.path().join("integrity.key").to_str().unwrap());
+    assert_eq!(IntegrityConfig::load().unwrap().replicas, 3);
+
+    // Another key or no key at all doesn't match
+    root.set_env(KEY_ENV, "other");
+    assert!(matches!(
+        IntegrityConfig::load(),
+        Err(ConfigError::IntegrityMismatch(_))
+    ));
+    root.remove_env(KEY_ENV).remove_env(KEY_FILE_ENV);
+    assert!(matches!(
+        IntegrityConfig::load(),
+        Err(ConfigError::IntegrityMismatch(_))
+    ));
+}
+
+#[cfg(feature = "integrity")]
+#[test]
+fn test_integrity_checksum_with_key() {
+    use mtc_config::integrity::checksum;
+    let plain = checksum(b"replicas = 1\n", None);
+    let keyed = checksum(b"replicas = 1\n", Some(b"secret"));
+    assert!(plain.starts_with("sha256:"));
+    assert!(keyed.starts_with("hmac-sha256:"));
+    assert_ne!(keyed, checksum(b"replicas = 1\n", Some(b"other")));
+    assert_eq!(keyed, checksum(b"replicas = 1\n", Some(b"secret")));
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "isolated")]
+struct IsolatedConfig {
+    owner: String,
+    retries: u32,
+}
+
+#[test]
+fn test_isolated_root_seed_and_assert() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    assert_eq!(mtc_config::get_configs_dir(), root.path());
+    root.assert_not_saved::<IsolatedConfig>();
+
+    root.seed::<IsolatedConfig>("owner = \"seeded\"\nretries = 1\n");
+    let mut config = IsolatedConfig::load().unwrap();
+    assert_eq!(config.owner, "seeded");
+
+    config.retries = 4;
+    config.save().unwrap();
+    root.assert_saved(&config);
+    root.assert_saved_contains::<IsolatedConfig>("retries = 4");
+    assert_eq!(root.files(), [std::path::PathBuf::from("isolated.toml")]);
+}
+
+#[test]
+fn test_isolated_roots_do_not_share_files() {
+    // Same configuration name as the test above, running in parallel
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_config(&IsolatedConfig {
+        owner: "other".to_string(),
+        retries: 9,
+    });
+    let outer = root.path().to_path_buf();
+    {
+        let nested = mtc_config::testing::TestConfigRoot::new();
+        nested.assert_not_saved::<IsolatedConfig>();
+    }
+    assert_eq!(mtc_config::get_configs_dir(), outer);
+    assert_eq!(IsolatedConfig::load().unwrap().retries, 9);
+    drop(root);
+    assert!(!outer.exists());
+    assert_ne!(mtc_config::get_configs_dir(), outer);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, arbitrary::Arbitrary)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "round_trip")]
+struct RoundTripConfig {
+    name: String,
+    workers: u32,
+    verbose: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, arbitrary::Arbitrary)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "lossy_round_trip")]
+struct LossyRoundTripConfig {
+    limits: Vec<Option<u32>>,
+}
+
+#[test]
+fn test_round_trip_in_every_format() {
+    mtc_config::testing::assert_round_trip::<RoundTripConfig>(100);
+}
+
+#[test]
+fn test_round_trip_flags_toml_null() {
+    use mtc_config::ConfigFormat;
+
+    let report = mtc_config::testing::check_round_trip::<LossyRoundTripConfig>(100);
+    let failure = report.failure(ConfigFormat::Toml).unwrap();
+    assert!(failure.hint.unwrap().contains("skip_serializing_if"), "{report}");
+    assert!(!report.supported_formats().contains(&ConfigFormat::Toml));
+    assert!(report.to_string().contains("MTC_CONFIG_ROUND_TRIP_SEED"));
+}
+
+#[cfg(feature = "encryption")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, arbitrary::Arbitrary)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "unsaved_round_trip", encrypted)]
+struct UnsavedRoundTripConfig {
+    workers: u32,
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_round_trip_reports_failed_saves() {
+    use mtc_config::encryption::{KEY_ENV, KEY_FILE_ENV};
+    use mtc_config::testing::RoundTripProblem;
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.remove_env(KEY_ENV).remove_env(KEY_FILE_ENV);
+    let report = mtc_config::testing::check_round_trip::<UnsavedRoundTripConfig>(10);
+    let failure = report.failure(ConfigFormat::Toml).unwrap();
+    assert!(
+        matches!(&failure.problem, RoundTripProblem::Serialize(e) if e.contains("no key")),
+        "{report}"
+    );
+    assert!(failure.written.is_none());
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "templated")]
+struct TemplatedConfig {
+    package: String,
+    retries: u32,
+    configs_dir: String,
+}
+
+#[cfg(feature = "templates")]
+#[test]
+fn test_load_renders_template() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_config(&IsolatedConfig {
+        owner: "ops".to_string(),
+        retries: 2,
+    });
+    root.seed_file(
+        "templated.toml.j2",
+        "package = \"{{ env.CARGO_PKG_NAME }}\"\n\
+         retries = {{ configs.isolated.retries * 2 }}\n\
+         configs_dir = \"{{ workspace.configs_dir }}\"\n",
+    );
+
+    let config = TemplatedConfig::load().unwrap();
+    assert_eq!(config.package, "mtc-config");
+    assert_eq!(config.retries, 4);
+    assert_eq!(config.configs_dir, root.path().to_string_lossy());
+    root.assert_not_saved::<TemplatedConfig>();
+
+    let deployed = root.path().join("deploy").join("templated.toml");
+    assert_eq!(TemplatedConfig::render_to(&deployed).unwrap(), config);
+    assert!(std::fs::read_to_string(deployed).unwrap().contains("retries = 4"));
+
+    assert_eq!(TemplatedConfig::materialize().unwrap(), config);
+    root.assert_saved(&config);
+}
+
+#[cfg(feature = "templates")]
+#[test]
+fn test_template_errors_name_the_cause() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_file("templated.toml.j2", "retries = {{ configs.missing.retries }}\n");
+    let error = TemplatedConfig::load().unwrap_err().to_string();
+    assert!(error.contains("no configuration named `missing`"), "{error}");
+
+    root.seed_file("templated.toml.j2", "retries = {{ configs.templated.retries }}\n");
+    let error = TemplatedConfig::load().unwrap_err().to_string();
+    assert!(error.contains("uses itself"), "{error}");
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+struct EnvDatabase {
+    url: String,
+    pool: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "env_export", env_prefix = "MTC_ENV_TEST")]
+struct EnvExportConfig {
+    port: u16,
+    label: String,
+    token: Option<String>,
+    hosts: Vec<String>,
+    database: EnvDatabase,
+}
+
+fn env_export_config() -> EnvExportConfig {
+    EnvExportConfig {
+        port: 8080,
+        label: "42".to_string(),
+        token: Some("1234".to_string()),
+        hosts: vec!["a".to_string(), "b c".to_string()],
+        database: EnvDatabase {
+            url: "postgres://db/app?ssl=true".to_string(),
+            pool: 4,
+        },
+    }
+}
+
+#[test]
+fn test_to_env_round_trips_through_with_env() {
+    let config = env_export_config();
+    let vars = config.to_env("APP").unwrap();
+    assert_eq!(
+        vars,
+        [
+            ("APP_DATABASE__POOL", "4"),
+            ("APP_DATABASE__URL", "postgres://db/app?ssl=true"),
+            ("APP_HOSTS", "[\"a\",\"b c\"]"),
+            ("APP_LABEL", "42"),
+            ("APP_PORT", "8080"),
+            ("APP_TOKEN", "1234"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    );
+    assert_eq!(EnvExportConfig::default().with_env("APP", vars).unwrap(), config);
+
+    let wrong = [("APP_PORT".to_string(), "http".to_string())];
+    assert!(EnvExportConfig::default().with_env("APP", wrong).is_err());
+    assert!(matches!(
+        EnvExportConfig::default().with_env("", std::env::vars()),
+        Err(ConfigError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn test_env_export_styles() {
+    use mtc_config::environment::{EnvStyle, export};
+
+    let vars = [("APP_NAME".to_string(), "it's $HOME".to_string())];
+    assert_eq!(export(&vars, EnvStyle::Dotenv), "APP_NAME=\"it's $HOME\"\n");
+    assert_eq!(
+        export(&vars, EnvStyle::Export),
+        "export APP_NAME='it'\\''s $HOME'\n"
+    );
+    assert_eq!(
+        export(&vars, "compose".parse().unwrap()),
+        "environment:\n  APP_NAME: \"it's $$HOME\"\n"
+    );
+}
+
+#[test]
+fn test_load_applies_env_overrides() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_config(&env_export_config())
+        .set_env("MTC_ENV_TEST_DATABASE__POOL", "9");
+
+    let config = EnvExportConfig::load().unwrap();
+    assert_eq!(config.database.pool, 9);
+    assert_eq!(config.port, 8080);
+    root.assert_saved_contains::<EnvExportConfig>("pool = 4");
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "edited")]
+struct EditedConfig {
+    /// Listen port
+    #[config(min = 1)]
+    port: u16,
+    host: String,
+    #[config(secret)]
+    token: String,
+}
+
+impl Default for EditedConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            host: "localhost".to_string(),
+            token: "default-token".to_string(),
+        }
+    }
+}
+
+#[test]
+fn test_editor_prompts_validates_and_saves() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed::<EditedConfig>("port = 80\nhost = \"web\"\ntoken = \"old-token\"\n");
+
+    let mut input = std::io::Cursor::new("0\nabc\n9000\n\n~\ny\n");
+    let mut output = Vec::new();
+    let saved = mtc_config::editor::edit_with::<EditedConfig>(&mut input, &mut output)
+        .unwrap()
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let expected = EditedConfig {
+        port: 9000,
+        host: "web".to_string(),
+        token: "default-token".to_string(),
+    };
+    assert_eq!(saved, expected);
+    root.assert_saved(&expected);
+    assert!(output.contains("port (u16): Listen port"), "{output}");
+    assert!(output.contains("rules: min = 1"), "{output}");
+    assert!(output.contains("must be at least 1"), "{output}");
+    assert!(output.contains("~ port: 80 -> 9000"), "{output}");
+    assert!(output.contains("~ token: *secret*"), "{output}");
+    assert!(!output.contains("old-token"), "{output}");
+}
+
+#[test]
+fn test_editor_saves_nothing_when_declined_or_interrupted() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let mut output = Vec::new();
+
+    let mut input = std::io::Cursor::new("1\n\n\nn\n");
+    let edited = mtc_config::editor::edit_with::<EditedConfig>(&mut input, &mut output);
+    assert!(edited.unwrap().is_none());
+
+    let mut input = std::io::Cursor::new("1\n");
+    let edited = mtc_config::editor::edit_with::<EditedConfig>(&mut input, &mut output);
+    assert!(edited.is_err());
+    root.assert_not_saved::<EditedConfig>();
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "linted")]
+#[serde(default)]
+struct LintedConfig {
+    name: String,
+    workers: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "linted_limits")]
+struct LintedLimitsConfig {
+    #[config(max = 8)]
+    workers: u32,
+}
+
+#[test]
+fn test_lint_dir_reports_every_problem() {
+    use mtc_config::lint::{LintKind, Severity};
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_file("linted.toml", "name = \"a\"\ncolour = \"red\"\n");
+    root.seed_file("linted.toml.sum", "sha256:00\n");
+    root.seed_file("linted_limits.toml", "workers = 99\n");
+    root.seed_file("isolated.toml", "owner = 1\nretries = 2\n");
+    root.seed_file("nobody.toml", "x = 1\n");
+    root.seed_file(".history/linted/old.toml", "name = 1\n");
+
+    let report = mtc_config::lint_dir(root.path()).unwrap();
+    assert_eq!(report.files.len(), 4);
+    let found: Vec<_> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.path.to_str().unwrap(), issue.kind, issue.key.as_deref()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("isolated.toml", LintKind::Parse, None),
+            ("linted.toml", LintKind::UnknownKey, Some("colour")),
+            ("linted.toml", LintKind::Stale, Some("workers")),
+            ("linted_limits.toml", LintKind::Validation, None),
+            ("nobody.toml", LintKind::Orphan, None),
+        ]
+    );
+    assert_eq!(report.issues[1].severity, Severity::Warning);
+    assert_eq!(report.issues[3].severity, Severity::Error);
+    assert!(report.has_errors());
+    assert!(report.to_json().unwrap().contains("\"kind\": \"unknown_key\""));
+    assert!(report.to_string().ends_with("4 files checked, 2 errors, 3 warnings"));
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "typed_values")]
+struct TypedValuesConfig {
+    timeout: mtc_config::types::Duration,
+    max_upload: mtc_config::types::ByteSize,
+    data_dir: mtc_config::types::ExpandedPath,
+    listen: mtc_config::types::SocketAddr,
+    retry_after: Option<mtc_config::types::Duration>,
+}
+
+impl Default for TypedValuesConfig {
+    fn default() -> Self {
+        Self {
+            timeout: mtc_config::types::Duration::from_secs(90),
+            max_upload: mtc_config::types::ByteSize::mib(10),
+            data_dir: "data".parse().unwrap(),
+            listen: ":8080".parse().unwrap(),
+            retry_after: None,
+        }
+    }
+}
+
+#[test]
+fn test_value_types_parse_and_display() {
+    use mtc_config::types::{ByteSize, Duration, ExpandedPath, SocketAddr};
+
+    let duration: Duration = "1h 30m".parse().unwrap();
+    assert_eq!(duration.as_secs(), 5400);
+    assert_eq!(duration.to_string(), "1h30m");
+    assert_eq!("1.5s".parse::<Duration>().unwrap().to_string(), "1s500ms");
+    let error = "30".parse::<Duration>().unwrap_err().to_string();
+    assert!(error.contains("`30` has no unit"), "{error}");
+    assert!("5 weeks".parse::<Duration>().is_err());
+
+    assert_eq!("10MiB".parse::<ByteSize>().unwrap(), ByteSize::mib(10));
+    assert_eq!("1.5 kb".parse::<ByteSize>().unwrap().bytes(), 1500);
+    assert_eq!(ByteSize(1536).to_string(), "1536B");
+    assert_eq!(ByteSize::kib(3).to_string(), "3KiB");
+    assert_eq!(ByteSize(2_000_000).to_string(), "2MB");
+    let error = "ten MB".parse::<ByteSize>().unwrap_err().to_string();
+    assert!(error.contains("expected a size like"), "{error}");
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.set_env("MTC_CONFIG_TYPES_DIR", "/srv");
+    let path: ExpandedPath = "${MTC_CONFIG_TYPES_DIR}/cache/$$1".parse().unwrap();
+    assert_eq!(path.expanded(), std::path::Path::new("/srv/cache/$1"));
+    assert_eq!(path.to_string(), "${MTC_CONFIG_TYPES_DIR}/cache/$$1");
+    let home: ExpandedPath = "~/data".parse().unwrap();
+    assert!(home.expanded().is_absolute());
+    assert_eq!(
+        "logs".parse::<ExpandedPath>().unwrap().relative_to("/var".as_ref()),
+        std::path::PathBuf::from("/var/logs")
+    );
+    let error = "$MTC_CONFIG_TYPES_UNSET/x".parse::<ExpandedPath>().unwrap_err();
+    assert!(error.to_string().contains("is not set"), "{error}");
+
+    assert_eq!(":8080".parse::<SocketAddr>().unwrap().to_string(), "0.0.0.0:8080");
+    assert_eq!(
+        "localhost:80".parse::<SocketAddr>().unwrap().to_string(),
+        "127.0.0.1:80"
+    );
+    let error = "example.com:80".parse::<SocketAddr>().unwrap_err().to_string();
+    assert!(error.contains("must be an IP address"), "{error}");
+}
+
+#[test]
+fn test_value_types_in_config_file() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let config = TypedValuesConfig {
+        retry_after: Some(mtc_config::types::Duration::from_millis(250)),
+        ..Default::default()
+    };
+    config.save().unwrap();
+    root.assert_saved_contains::<TypedValuesConfig>("timeout = \"1m30s\"");
+    root.assert_saved_contains::<TypedValuesConfig>("max_upload = \"10MiB\"");
+    root.assert_saved_contains::<TypedValuesConfig>("listen = \"0.0.0.0:8080\"");
+    assert_eq!(TypedValuesConfig::load().unwrap(), config);
+
+    root.seed_file(
+        "typed_values.toml",
+        "timeout = \"2m\"\nmax_upload = 1024\ndata_dir = \"data\"\nlisten = \":9000\"\n",
+    );
+    assert_eq!(TypedValuesConfig::load().unwrap().max_upload.bytes(), 1024);
+    root.seed_file(
+        "typed_values.toml",
+        "timeout = \"2 fortnights\"\nmax_upload = 1\ndata_dir = \"data\"\nlisten = \":9000\"\n",
+    );
+    let error = TypedValuesConfig::load().unwrap_err().to_string();
+    assert!(error.contains("unknown unit `fortnights`"), "{error}");
+
+    let schema = TypedValuesConfig::schema();
+    let formats: Vec<_> = schema.fields.iter().map(|field| field.value_format).collect();
+    assert_eq!(formats[0], Some(<mtc_config::types::Duration as mtc_config::ValueFormat>::FORMAT));
+    assert!(formats[1].unwrap().starts_with("size like"));
+    assert_eq!(formats[4], formats[0]);
+    assert_eq!(
+        EnvExportConfig::schema().fields[0].value_format,
+        None
+    );
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn test_url_value_type() {
+    let url: mtc_config::types::Url = "https://example.com/api".parse().unwrap();
+    assert_eq!(url.host_str(), Some("example.com"));
+    let error = "example.com".parse::<mtc_config::types::Url>().unwrap_err();
+    assert!(error.to_string().contains("expected a URL like"), "{error}");
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "tenant_db", multi, history = 2)]
+struct TenantDbConfig {
+    host: String,
+    #[config(min = 1)]
+    pool_size: u32,
+}
+
+#[test]
+fn test_multi_instances() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    assert!(TenantDbConfig::list_instances().unwrap().is_empty());
+
+    let acme = TenantDbConfig {
+        host: "acme.db".to_string(),
+        pool_size: 4,
+    };
+    let globex = TenantDbConfig {
+        host: "globex.db".to_string(),
+        pool_size: 8,
+    };
+    acme.save_instance("acme").unwrap();
+    globex.save_instance("globex").unwrap();
+    assert!(root.path().join("tenant_db/acme.toml").exists());
+    assert_eq!(TenantDbConfig::list_instances().unwrap(), ["acme", "globex"]);
+    assert_eq!(TenantDbConfig::load_instance("acme").unwrap(), acme);
+    assert_eq!(TenantDbConfig::load_instance("globex").unwrap(), globex);
+    root.assert_not_saved::<TenantDbConfig>();
+
+    let resized = TenantDbConfig {
+        pool_size: 16,
+        ..acme
+    };
+    resized.save_instance("acme").unwrap();
+    assert_eq!(TenantDbConfig::load_instance("acme").unwrap(), resized);
+    assert!(root.path().join(".history/tenant_db/acme").is_dir());
+
+    root.seed_file("tenant_db/broken.toml", "host = \"x\"\npool_size = 0\n");
+    assert!(matches!(
+        TenantDbConfig::load_instance("broken"),
+        Err(ConfigError::Validation(_))
+    ));
+    let report = mtc_config::lint_dir(root.path()).unwrap();
+    let linted: Vec<_> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.path.to_str().unwrap(), issue.config.as_deref()))
+        .collect();
+    assert_eq!(linted, [("tenant_db/broken.toml", Some("tenant_db"))]);
+
+    TenantDbConfig::remove_instance("globex").unwrap();
+    assert_eq!(TenantDbConfig::list_instances().unwrap(), ["acme", "broken"]);
+    assert!(matches!(
+        TenantDbConfig::remove_instance("globex"),
+        Err(ConfigError::NotFound(_))
+    ));
+    assert!(matches!(
+        TenantDbConfig::load_instance("../acme"),
+        Err(ConfigError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        LintedConfig::load_instance("acme"),
+        Err(ConfigError::InvalidArgument(_))
+    ));
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "shared_docs", section = "docs", file = "shared_ws")]
+struct SharedDocsConfig {
+    title: String,
+    authors: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "shared_build", section = "build", file = "shared_ws")]
+#[serde(default)]
+struct SharedBuildConfig {
+    jobs: u32,
+}
+
+#[test]
+fn test_sections_share_one_file() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    assert_eq!(SharedDocsConfig::default_path(), root.path().join("shared_ws.toml"));
+
+    let docs = SharedDocsConfig {
+        title: "Book".to_string(),
+        authors: None,
+    };
+    docs.save().unwrap();
+    SharedBuildConfig { jobs: 4 }.save().unwrap();
+    let content = std::fs::read_to_string(root.path().join("shared_ws.toml")).unwrap();
+    assert!(content.contains("[build]\njobs = 4"), "{content}");
+    assert!(content.contains("[docs]\ntitle = \"Book\""), "{content}");
+
+    SharedBuildConfig { jobs: 8 }.save().unwrap();
+    assert_eq!(SharedDocsConfig::load().unwrap(), docs);
+    assert_eq!(SharedBuildConfig::load().unwrap().jobs, 8);
+
+    root.seed_file("shared_ws.toml", "[build]\njobs = 2\n");
+    assert!(matches!(
+        SharedDocsConfig::load(),
+        Err(ConfigError::NotFound(_))
+    ));
+    SharedDocsConfig::load_or_default();
+    assert_eq!(SharedBuildConfig::load().unwrap().jobs, 2);
+    assert_eq!(SharedDocsConfig::load().unwrap(), SharedDocsConfig::default());
+    assert!(SharedDocsConfig::convert_to(ConfigFormat::Toml).is_err());
+}
+
+#[test]
+fn test_lint_dir_checks_shared_file_tables() {
+    use mtc_config::lint::LintKind;
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_file("shared_ws.toml", "[docs]\ntitle = 1\n\n[deploy]\nhost = \"x\"\n");
+    let report = mtc_config::lint_dir(root.path()).unwrap();
+    let found: Vec<_> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.config.as_deref(), issue.kind, issue.key.as_deref()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (Some("shared_build"), LintKind::Stale, Some("build")),
+            (Some("shared_docs"), LintKind::Parse, None),
+            (None, LintKind::UnknownKey, Some("deploy")),
+        ]
+    );
+}
+
+#[cfg(feature = "remote")]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "remote_flags", remote = "${MTC_CONFIG_TEST_SERVER}/flags.toml")]
+struct RemoteFlagsConfig {
+    enabled: bool,
+    level: u32,
+}
+
+/// Serves one canned response per connection on a local port, returning
+/// the base URL and the requests received.
+#[cfg(feature = "remote")]
+fn serve(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            while reader.read_line(&mut request).unwrap() > 2 && !request.ends_with("\r\n\r\n") {}
+            requests.push(request);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (base, server)
+}
+
+#[cfg(feature = "remote")]
+fn http_response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn test_remote_config_with_cache_and_fallback() {
+    use mtc_config::remote::{RemoteOrigin, RemoteSource};
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let (base, server) = serve(vec![
+        http_response("200 OK", "ETag: \"v1\"\r\n", "enabled = true\nlevel = 3\n"),
+        http_response("304 Not Modified", "", ""),
+        http_response("500 Internal Server Error", "", "down"),
+        http_response("200 OK", "", "level = \"high\"\n"),
+    ]);
+    root.set_env("MTC_CONFIG_TEST_SERVER", &base);
+
+    let expected = RemoteFlagsConfig {
+        enabled: true,
+        level: 3,
+    };
+    // Fetched, not modified, server error, then an invalid document
+    for _ in 0..4 {
+        assert_eq!(RemoteFlagsConfig::load().unwrap(), expected);
+    }
+    let requests = server.join().unwrap();
+    assert!(requests[0].starts_with("GET /flags.toml HTTP/1.1"));
+    assert!(!requests[0].contains("If-None-Match"));
+    assert!(requests[1].contains("If-None-Match: \"v1\""), "{}", requests[1]);
+    root.assert_not_saved::<RemoteFlagsConfig>();
+
+    // The server is gone
+    let source = RemoteSource::of::<RemoteFlagsConfig>().unwrap();
+    assert_eq!(source.cache_path(), root.path().join(".remote/remote_flags.toml"));
+    let cached = source.fetch().unwrap();
+    assert_eq!(cached.origin, RemoteOrigin::Cached);
+    assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+    assert!(cached.error.unwrap().contains("can't reach"));
+
+    std::fs::remove_dir_all(root.path().join(".remote")).unwrap();
+    root.seed_file("remote_flags.toml", "enabled = false\nlevel = 1\n");
+    assert_eq!(RemoteFlagsConfig::load().unwrap().level, 1);
+    assert!(matches!(
+        RemoteFlagsConfig::load_remote(),
+        Err(ConfigError::Remote(_))
+    ));
+
+    // Credentials in the URL never reach errors or logs
+    let source = RemoteSource::new(base.replace("://", "://admin:hunter2@") + "/flags.toml", root.path().join("private.toml"));
+    let Err(ConfigError::Remote(message)) = source.fetch() else {
+        panic!("the server is gone");
+    };
+    assert!(message.contains("http://*secret*@127.0.0.1"), "{message}");
+    assert!(!message.contains("hunter2"), "{message}");
+}
+
+/// Compact JSON, standing in for an application's own format.
+struct CompactJsonCodec {
+    name: &'static str,
+    extension: &'static str,
+}
+
+impl mtc_config::ConfigCodec for CompactJsonCodec {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extension(&self) -> &'static str {
+        self.extension
+    }
+
+    fn serialize(&self, value: &serde_json::Value) -> Result<String, ConfigError> {
+        serde_json::to_string(value).map_err(|e| ConfigError::Serialization(e.to_string()))
+    }
+
+    fn deserialize(&self, content: &str) -> Result<serde_json::Value, ConfigError> {
+        serde_json::from_str(content).map_err(|e| ConfigError::Deserialization(e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[cfg_attr(test, derive(mtc_config::Configuration))]
+#[config(name = "codec_app", codec = "compact_json")]
+struct CodecAppConfig {
+    name: String,
+    port: u16,
+}
+
+#[test]
+fn test_registered_codec() {
+    use mtc_config::FormatRegistry;
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    let config = CodecAppConfig {
+        name: "app".to_string(),
+        port: 8080,
+    };
+    let error = config.save().unwrap_err();
+    assert!(error.to_string().contains("`compact_json` is not registered"), "{error}");
+
+    let codec = |name, extension| CompactJsonCodec { name, extension };
+    let format = FormatRegistry::register(codec("compact_json", "cjson")).unwrap();
+    assert_eq!(format, ConfigFormat::Custom("compact_json"));
+    assert_eq!("cjson".parse::<ConfigFormat>().unwrap(), format);
+    assert_eq!(format.extension(), "cjson");
+    assert!(FormatRegistry::formats().contains(&format));
+    for (name, extension) in [("compact_json", "json2"), ("toml", "toml"), ("yml", "yml"), ("json3", "env")] {
+        assert!(matches!(
+            FormatRegistry::register(codec(name, extension)),
+            Err(ConfigError::InvalidArgument(_))
+        ));
+    }
+
+    config.save().unwrap();
+    assert_eq!(CodecAppConfig::default_path(), root.path().join("codec_app.cjson"));
+    root.assert_saved_contains::<CodecAppConfig>(r#"{"name":"app","port":8080}"#);
+    assert_eq!(CodecAppConfig::load().unwrap(), config);
+
+    // Files in a registered format are found like those of the fallback extensions
+    std::fs::remove_file(CodecAppConfig::default_path()).unwrap();
+    root.seed_file("codec_app.toml", "name = \"seeded\"\nport = 1\n");
+    assert_eq!(CodecAppConfig::load().unwrap().name, "seeded");
+    assert_eq!(
+        CodecAppConfig::convert_to(format).unwrap(),
+        root.path().join("codec_app.cjson")
+    );
+    root.assert_saved_contains::<CodecAppConfig>(r#"{"name":"seeded","port":1}"#);
+}
+
+#[test]
+fn test_section_migrates_standalone_file() {
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_file("shared_ws.toml", "[build]\njobs = 2\n");
+    root.seed_file("shared_docs.toml", "title = \"Legacy\"\n");
+
+    let docs = SharedDocsConfig::load().unwrap();
+    assert_eq!(docs.title, "Legacy");
+    assert_eq!(root.files(), [std::path::PathBuf::from("shared_ws.toml")]);
+    assert_eq!(SharedBuildConfig::load().unwrap().jobs, 2);
+    assert_eq!(SharedDocsConfig::load().unwrap(), docs);
+
+    root.seed_file("shared_docs.toml", "title = \"Only legacy\"\n");
+    std::fs::remove_file(root.path().join("shared_ws.toml")).unwrap();
+    assert_eq!(SharedDocsConfig::load().unwrap().title, "Only legacy");
+    root.assert_saved_contains::<SharedDocsConfig>("[docs]\ntitle = \"Only legacy\"");
+}
+
+#[test]
+fn test_lint_dir_reports_renamed_keys_once() {
+    use mtc_config::lint::LintKind;
+
+    let root = mtc_config::testing::TestConfigRoot::new();
+    root.seed_file("linted.toml", "name = \"a\"\nthreads = 4\n");
+    let report = mtc_config::lint_dir(root.path()).unwrap();
+    let found: Vec<_> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.kind, issue.key.as_deref()))
+        .collect();
+    assert_eq!(found, [(LintKind::Stale, Some("workers"))]);
+    assert!(report.issues[0].message.contains("`threads`"), "{report}");
 }
 
 }
\ No newline at end of file
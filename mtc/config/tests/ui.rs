@@ -0,0 +1,15 @@
+//! Compile-fail tests for the errors reported by `#[derive(Configuration)]`.
+
+#[test]
+fn derive_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+    #[cfg(not(feature = "yaml"))]
+    t.compile_fail("tests/ui/no_yaml/*.rs");
+    #[cfg(not(feature = "encryption"))]
+    t.compile_fail("tests/ui/no_encryption/*.rs");
+    #[cfg(not(feature = "integrity"))]
+    t.compile_fail("tests/ui/no_integrity/*.rs");
+    #[cfg(not(feature = "remote"))]
+    t.compile_fail("tests/ui/no_remote/*.rs");
+}
@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "default_file_extension", default_file = "defaults/app.xml")]
+struct DefaultFileExtension {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "first", name = "second")]
+struct DuplicateKey {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "empty_env_prefix", env_prefix = "")]
+struct EmptyEnvPrefix {
+    value: u32,
+}
+
+fn main() {}
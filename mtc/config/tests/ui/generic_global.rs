@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(global)]
+struct Generic<T> {
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "encrypted_feature", encrypted)]
+struct EncryptedFeature {
+    password: String,
+}
+
+fn main() {}
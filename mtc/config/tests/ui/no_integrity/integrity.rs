@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "integrity_feature", integrity)]
+struct IntegrityFeature {
+    value: u32,
+}
+
+fn main() {}
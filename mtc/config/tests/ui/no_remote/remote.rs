@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "remote_feature", remote = "https://config.example.com/app.toml")]
+struct RemoteFeature {
+    value: u32,
+}
+
+fn main() {}
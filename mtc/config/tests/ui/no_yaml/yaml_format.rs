@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "yaml_feature", format = "yaml")]
+struct YamlFeature {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(remote = "https://config.example.com/app.toml", encrypted)]
+struct RemoteEncrypted {
+    value: u32,
+}
+
+fn main() {}
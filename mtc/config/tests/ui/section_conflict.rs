@@ -0,0 +1,15 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(section = "tool", file = "workspace", multi, migrate)]
+struct SectionConflict {
+    value: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(file = "workspace")]
+struct FileWithoutSection {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+struct Pair(u32, u32);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mtc_config::Configuration;
+
+#[derive(Configuration)]
+union Raw {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
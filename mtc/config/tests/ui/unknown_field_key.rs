@@ -0,0 +1,10 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "unknown_field_key")]
+struct UnknownFieldKey {
+    #[config(secert)]
+    token: String,
+}
+
+fn main() {}
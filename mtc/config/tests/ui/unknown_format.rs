@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "unknown_format", format = "xml")]
+struct UnknownFormat {
+    value: u32,
+}
+
+fn main() {}
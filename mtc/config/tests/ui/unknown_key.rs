@@ -0,0 +1,9 @@
+use mtc_config::{Configuration, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default, Configuration)]
+#[config(name = "unknown_key", fromat = "json")]
+struct UnknownKey {
+    value: u32,
+}
+
+fn main() {}
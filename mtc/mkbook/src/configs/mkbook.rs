@@ -9,8 +9,8 @@ use crate::configs::bookconfig::BookConfig;
 
 // fn map_env_to_vec(v:)
 
-#[derive(Debug,Default,Configuration,Serialize,Deserialize)]
-#[config(name="mkbook", format="toml")]
+#[derive(Debug,Clone,Default,Configuration,Serialize,Deserialize)]
+#[config(name="mkbook", format="toml", section="mkbook", file="workspace", global)]
 pub struct Config{
     #[default(String::from("book"))]
     pub dir:String,
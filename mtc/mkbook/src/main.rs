@@ -22,6 +22,35 @@ pub fn main_with_args(args: &[String]) -> AnyResult<()> {
         .subcommand(Command::new("install").about("install tools needed").alias("i"))
         .subcommand(Command::new("vars"))
         .subcommand(Command::new("generate").alias("gen"))
+        .subcommand(
+            Command::new("lint")
+                .about("check every config file against its type")
+                .arg(Arg::new("dir").help("configs directory, the default one if omitted"))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("print the report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("edit")
+                .about("edit a config interactively, the mkbook config by default")
+                .arg(Arg::new("config").default_value("mkbook")),
+        )
+        .subcommand(
+            Command::new("env")
+                .about("print a config as environment variables")
+                .arg(Arg::new("config").required(true))
+                .arg(Arg::new("prefix").short('p').long("prefix"))
+                .arg(
+                    Arg::new("style")
+                        .short('s')
+                        .long("style")
+                        .help("dotenv, export or compose")
+                        .default_value("dotenv"),
+                ),
+        )
         .subcommand(Command::new("ci"));
 
     let matches = cli.get_matches_from(args);
@@ -42,6 +71,20 @@ pub fn main_with_args(args: &[String]) -> AnyResult<()> {
         Some(("generate",_))=>{
             mtc_make_a_book::tasks::book::make_book()
         }
+        Some(("lint", args)) => {
+            let dir = args.get_one::<String>("dir").map(std::path::PathBuf::from);
+            mtc_make_a_book::tasks::config::lint(dir, args.get_flag("json"))
+        }
+        Some(("edit", args)) => {
+            let name = args.get_one::<String>("config").unwrap();
+            mtc_make_a_book::tasks::config::edit_config(name)
+        }
+        Some(("env", args)) => {
+            let name = args.get_one::<String>("config").unwrap();
+            let prefix = args.get_one::<String>("prefix").map(String::as_str);
+            let style = args.get_one::<String>("style").unwrap();
+            mtc_make_a_book::tasks::config::export_env(name, prefix, style)
+        }
         Some(("ci", _)) | None => mtc_make_a_book::tasks::ci::ci(),
         _ => {
             eprintln!("Error: Unrecognized subcommand");
@@ -1,9 +1,10 @@
 use std::fs::write;
+use std::sync::Arc;
 
 use anyhow::{anyhow,Result};
 use better_default::Default;
 use duct::cmd;
-use mtc_config::{Configuration, Deserialize, Serialize};
+use mtc_config::{Deserialize, Serialize};
 use mtc_toolbelt::{env_utils::get_workspace_root, get_git_default_branch_name};
 use tracing::info;
 
@@ -11,27 +12,87 @@ use crate::configs::{bookconfig::BookConfig, mkbook::Config};
 
 // fn map_env_to_vec(v:)
 
- fn get_config()->Config{
+ fn get_config()->Arc<Config>{
 
 
-    Config::load_or_default()
+    Config::global()
 }
 
 pub fn make_book()->Result<()>{
-    let config: Config=get_config();
+    let config=get_config();
     info!("config: {:#?}",config);
     let book_path=get_workspace_root().join(&config.dir);
-    let book_config=BookConfig::from(config);
+    let book_config=BookConfig::from(Config::clone(&config));
     // println!("{}",book_config.to_toml());
 
     std::fs::create_dir_all(&book_path)?;
     std::fs::create_dir_all(&book_path.join("src"))?;
     std::fs::write(&book_path.join("book.toml"), book_config.to_toml())?;
-    std::fs::write(&book_path.join("src/SUMMARY.md"), "# SUMMARY\n\n- [readme](<README.md>)\n")?;
+    std::fs::write(book_path.join("src/SUMMARY.md"), "# SUMMARY\n\n- [readme](<README.md>)\n- [configurations](<configs.md>)\n")?;
     std::fs::write(&book_path.join("src/README.md"), "# README\n\nhi\n")?;
+    std::fs::write(book_path.join("src/configs.md"), configs_page())?;
     std::fs::write(&book_path.join(".gitignore"), "book/\n")?;
 
 
 
     Ok(())
 }
+
+/// Renders a page describing every configuration type registered in this binary.
+fn configs_page() -> String {
+    let root = get_workspace_root();
+    let mut page = String::from("# Configurations\n\n");
+    for config in mtc_config::registered_configs() {
+        let full_path = config.default_path();
+        let path = full_path.strip_prefix(&root).unwrap_or(full_path.as_path());
+        page.push_str(&format!("## {}\n\n", config.config_name()));
+        page.push_str(&format!("- type: `{}`\n", config.type_name));
+        page.push_str(&format!("- format: `{}`\n", config.format()));
+        page.push_str(&format!("- path: `{}`\n\n", path.display()));
+        let schema = config.schema();
+        if !schema.doc.is_empty() {
+            page.push_str(&format!("{}\n\n", schema.doc));
+        }
+        fields_table(&mut page, &schema, "");
+    }
+    page
+}
+
+/// Renders the fields of a schema as a markdown table, nested sections inline.
+fn fields_table(page: &mut String, schema: &mtc_config::ConfigSchema, prefix: &str) {
+    if schema.fields.is_empty() {
+        return;
+    }
+    if prefix.is_empty() {
+        page.push_str("| field | type | default | rules | description |\n");
+        page.push_str("|---|---|---|---|---|\n");
+    }
+    for field in &schema.fields {
+        let default = if field.secret {
+            "*secret*".to_string()
+        } else {
+            field
+                .default
+                .as_deref()
+                .map(|value| format!("`{value}`"))
+                .unwrap_or_default()
+        };
+        let rules: Vec<String> = field.rules.iter().map(ToString::to_string).collect();
+        let mut description = field.doc.replace('\n', " ");
+        if let Some(format) = field.value_format {
+            description = format!("{description} Expects a {format}.").trim_start().to_string();
+        }
+        page.push_str(&format!(
+            "| `{prefix}{}` | `{}` | {default} | {} | {description} |\n",
+            field.name,
+            field.type_name,
+            rules.join(", "),
+        ));
+        if let Some(nested) = &field.nested {
+            fields_table(page, nested, &format!("{prefix}{}.", field.name));
+        }
+    }
+    if prefix.is_empty() {
+        page.push('\n');
+    }
+}
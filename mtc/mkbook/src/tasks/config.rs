@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use mtc_config::environment::{self, EnvStyle};
+
+/// Prints a registered configuration as environment variables.
+///
+/// The prefix defaults to the `env_prefix` of the configuration, else its
+/// name in uppercase. `style` is one of `dotenv`, `export` or `compose`.
+pub fn export_env(name: &str, prefix: Option<&str>, style: &str) -> Result<()> {
+    let config = mtc_config::find_registered_config(name)
+        .ok_or_else(|| anyhow!("no configuration named `{name}`"))?;
+    let style: EnvStyle = style.parse()?;
+    let prefix = prefix
+        .map(str::to_string)
+        .or_else(|| config.env_prefix())
+        .unwrap_or_else(|| name.to_uppercase());
+    let vars = environment::flatten(&config.load()?, &prefix);
+    print!("{}", environment::export(&vars, style));
+    Ok(())
+}
+
+/// Edits a registered configuration on the terminal, prompting for every field.
+pub fn edit_config(name: &str) -> Result<()> {
+    let config = mtc_config::find_registered_config(name)
+        .ok_or_else(|| anyhow!("no configuration named `{name}`"))?;
+    if config.edit()? {
+        println!("saved {}", config.default_path().display());
+    }
+    Ok(())
+}
+
+/// Lints every file in `dir`, or in the configs directory, failing if any
+/// of them doesn't load.
+pub fn lint(dir: Option<PathBuf>, json: bool) -> Result<()> {
+    let dir = dir.unwrap_or_else(mtc_config::get_configs_dir);
+    let report = mtc_config::lint_dir(&dir)?;
+    if json {
+        println!("{}", report.to_json()?);
+    } else {
+        println!("{report}");
+    }
+    if report.has_errors() {
+        return Err(anyhow!("some config files in {} don't load", dir.display()));
+    }
+    Ok(())
+}
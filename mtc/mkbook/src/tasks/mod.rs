@@ -1,2 +1,3 @@
 pub mod ci;
-pub mod book;
\ No newline at end of file
+pub mod book;
+pub mod config;
\ No newline at end of file